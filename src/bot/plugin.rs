@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use tetris_core::bot::{Bot, Weights};
+
+use crate::game_rules::resources::{AppState, PausedForClear};
+use crate::game_rules::systems::{piece_move, piece_spawn};
+use crate::replay::resources::ReplayPlayback;
+
+use super::resources::*;
+use super::systems::*;
+
+/// Let a bot play the game, it can be switched on and off at any time through
+/// the `BotPlayer` resource.
+pub(crate) struct BotPlugin {
+    pub(crate) enabled: bool,
+    pub(crate) weights: Weights,
+    /// Time between two inputs of the bot
+    pub(crate) delay: Duration,
+}
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        let bot = Bot {
+            weights: self.weights,
+        };
+
+        app.insert_resource(BotPlayer {
+            enabled: self.enabled,
+            ..BotPlayer::new(bot, self.delay)
+        })
+        .add_systems(
+            FixedUpdate,
+            bot_play
+                .after(piece_spawn)
+                .before(piece_move)
+                .run_if(|bot: Res<BotPlayer>| bot.enabled)
+                .run_if(not(resource_exists::<PausedForClear>))
+                .run_if(not(resource_exists::<ReplayPlayback>))
+                .run_if(in_state(AppState::Playing)),
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use tetris_core::bot::Bot;
+
+/// Automatic player, which takes control of the falling piece while enabled.
+#[derive(Resource, Default)]
+pub(crate) struct BotPlayer {
+    pub(crate) enabled: bool,
+    pub(crate) bot: Bot,
+    /// Time between two inputs, the bot plays as fast as possible when zero
+    pub(crate) delay: Duration,
+    /// Time since the last input
+    pub(crate) cooldown: Timer,
+}
+
+impl BotPlayer {
+    /// Delay between inputs which keeps the game easy to follow.
+    pub(crate) const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+    pub(crate) fn new(bot: Bot, delay: Duration) -> Self {
+        Self {
+            enabled: false,
+            bot,
+            delay,
+            cooldown: Timer::new(delay, TimerMode::Once),
+        }
+    }
+}
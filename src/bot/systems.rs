@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use tetris_core::rotation::Kicks180;
+
+use crate::game_rules::components::{Fall, GridPos, PieceKind, Spin};
+use crate::game_rules::resources::{
+    GridState, HeldPiece, PieceGenerator, PlayerInput, PlayerInputQueue, SoftDrop,
+};
+
+use super::resources::*;
+
+/// Queue the inputs that bring the falling piece to its best placement. A row
+/// down is reached by holding the soft drop until gravity moves the piece, the
+/// plan is then made again from there.
+///
+/// Without delay all other inputs are sent at once, otherwise a single input
+/// is sent every time the delay elapsed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn bot_play(
+    mut bot: ResMut<BotPlayer>,
+    time: Res<Time>,
+    grid: Res<GridState>,
+    held: Res<HeldPiece>,
+    kicks_180: Res<Kicks180>,
+    soft_drop: Res<SoftDrop>,
+    mut piece_generator: ResMut<PieceGenerator>,
+    mut player_inputs: ResMut<PlayerInputQueue>,
+    piece: Query<(&PieceKind, &GridPos, &Spin), With<Fall>>,
+) {
+    bot.cooldown.tick(time.delta());
+
+    // Inputs of the player are applied first
+    if !player_inputs.is_empty() || !bot.cooldown.finished() {
+        return;
+    }
+
+    let Ok((&kind, &pos, &spin)) = piece.get_single() else {
+        return;
+    };
+
+    let hold = if held.locked {
+        None
+    } else {
+        held.kind.or_else(|| piece_generator.peek_n(1).next())
+    };
+
+    let mut release = soft_drop.active;
+
+    for input in bot.bot.plan(&grid, kind, pos, spin, hold, *kicks_180) {
+        if input == PlayerInput::SoftDrop {
+            if !soft_drop.active {
+                player_inputs.push_back(input);
+            }
+
+            break;
+        }
+
+        if release {
+            player_inputs.push_back(PlayerInput::SoftDropRelease);
+            release = false;
+        }
+
+        player_inputs.push_back(input);
+
+        if !bot.delay.is_zero() {
+            break;
+        }
+    }
+
+    if !player_inputs.is_empty() {
+        bot.cooldown.reset();
+    }
+}
@@ -1,2 +1,3 @@
 pub(crate) mod plugin;
 pub(crate) mod resources;
+pub(crate) mod storage;
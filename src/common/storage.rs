@@ -0,0 +1,45 @@
+//! Persistent storage of small files, such as settings or scores. They are
+//! saved in the config directory of the user, or the local storage on web.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    fn path(name: &str) -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("tetris").join(name))
+    }
+
+    pub(crate) fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(path(name)?).ok()
+    }
+
+    pub(crate) fn write(name: &str, data: &str) -> Result<(), String> {
+        let path = path(name).ok_or("no config directory")?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        std::fs::write(&path, data).map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub(crate) fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(name).ok()?
+    }
+
+    pub(crate) fn write(name: &str, data: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or("no local storage")?
+            .set_item(name, data)
+            .map_err(|err| format!("{err:?}"))
+    }
+}
+
+pub(crate) use platform::{read, write};
@@ -1,127 +1,65 @@
 use bevy::prelude::*;
 
-// -- Spin
+pub(crate) use tetris_core::piece::{GridPos, PieceKind, Spin};
+use tetris_core::rotation::Rotation;
 
-#[derive(Component, Clone, Copy, Default)]
-pub(crate) struct Spin(pub(crate) u8);
-
-// -- PieceKind
+use super::resources::{LockDelay, MAX_LOCK_RESETS};
 
-#[derive(Component, Clone, Copy, Eq, PartialEq, Hash, Debug, enum_map::Enum)]
-pub(crate) enum PieceKind {
-    I,
-    O,
-    T,
-    S,
-    Z,
-    J,
-    L,
+#[derive(Component)]
+pub(crate) struct FilledCell {
+    pub(crate) color_from_kind: PieceKind,
 }
 
-impl PieceKind {
-    pub(crate) const fn all() -> [Self; 7] {
-        [
-            Self::I,
-            Self::O,
-            Self::T,
-            Self::S,
-            Self::Z,
-            Self::J,
-            Self::L,
-        ]
-    }
-
-    pub(crate) const fn base_shape(self) -> [[i8; 2]; 4] {
-        match self {
-            PieceKind::I => [[-2, 0], [-1, 0], [0, 0], [1, 0]],
-            PieceKind::O => [[-1, -1], [0, -1], [-1, 0], [0, 0]],
-            PieceKind::T => [[-1, 0], [0, 0], [1, 0], [0, 1]],
-            PieceKind::S => [[-1, 0], [0, 0], [0, 1], [1, 1]],
-            PieceKind::Z => [[-1, 1], [0, 0], [0, 1], [1, 0]],
-            PieceKind::J => [[-1, 0], [0, 0], [1, 0], [-1, 1]],
-            PieceKind::L => [[-1, 0], [0, 0], [1, 0], [1, 1]],
-        }
-    }
-
-    /// Wall kick directions when increasing angle.
-    /// See https://tetris.fandom.com/wiki/SRS
-    pub(crate) const fn wall_kick_incr_dirs(self) -> [[[i8; 2]; 4]; 4] {
-        match self {
-            Self::I => [
-                [[-2, 0], [1, 0], [-2, -1], [1, 2]],
-                [[-1, 0], [2, 0], [-1, 2], [2, -1]],
-                [[2, 0], [-1, 0], [2, 1], [-1, 2]],
-                [[1, 0], [-2, 0], [1, -2], [-2, 1]],
-            ],
-            _ => [
-                [[-1, 0], [-1, 1], [0, -2], [-1, -2]],
-                [[1, 0], [1, -1], [0, 2], [1, 2]],
-                [[1, 0], [1, 1], [0, -2], [1, -2]],
-                [[-1, 0], [-1, -1], [0, 2], [-1, 2]],
-            ],
-        }
-    }
-
-    const fn rotation(self, spin: Spin) -> [[i8; 2]; 4] {
-        let mut cells = self.base_shape();
-        let mut steps = spin.0 % 4;
-        let bbox_is_even = (1 - self.base_width() % 2) as i8;
-
-        while steps > 0 {
-            let mut i = 0;
+#[derive(Component, Clone)]
+pub(crate) struct Fall {
+    pub(crate) down_timer: Timer,
+    pub(crate) lock_timer: Timer,
+    /// Lowest row reached by the piece
+    pub(crate) lowest_row: u8,
+    /// Number of times the lock delay was reset since the lowest row was
+    /// reached
+    pub(crate) lock_resets: u32,
+}
 
-            while i < 4 {
-                cells[i] = [cells[i][1], -cells[i][0] - bbox_is_even];
-                i += 1;
+impl Fall {
+    /// The piece moved or rotated while lying on the ground.
+    pub(crate) fn grounded_move(&mut self, policy: LockDelay) {
+        match policy {
+            LockDelay::Extended => {
+                if self.lock_resets < MAX_LOCK_RESETS {
+                    self.lock_resets += 1;
+                    self.lock_timer.reset();
+                }
             }
-
-            steps -= 1;
+            LockDelay::Infinite => self.lock_timer.reset(),
+            LockDelay::Step => {}
         }
-
-        cells
     }
 
-    pub(crate) const fn base_width(self) -> u8 {
-        match self {
-            PieceKind::I => 4,
-            PieceKind::O => 2,
-            PieceKind::T | PieceKind::S | PieceKind::Z | PieceKind::J | PieceKind::L => 3,
+    /// Update lowest row reached, which resets the lock delay.
+    pub(crate) fn reach_row(&mut self, row: u8) {
+        if row < self.lowest_row {
+            self.lowest_row = row;
+            self.lock_resets = 0;
+            self.lock_timer.reset();
         }
     }
 
-    pub(crate) fn piece_covered_cells(
-        self,
-        pos: GridPos,
-        spin: Spin,
-    ) -> impl Iterator<Item = GridPos> {
-        self.rotation(spin).into_iter().map(move |[x, y]| GridPos {
-            x: pos.x.wrapping_add_signed(x),
-            y: pos.y.wrapping_add_signed(y),
-        })
+    /// No more resets are allowed, the piece must lock as soon as it lies
+    /// on the ground.
+    pub(crate) fn out_of_resets(&self, policy: LockDelay) -> bool {
+        policy == LockDelay::Extended && self.lock_resets >= MAX_LOCK_RESETS
     }
 }
 
-#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
-pub(crate) struct GridPos {
-    pub(crate) x: u8,
-    pub(crate) y: u8,
-}
-
-impl std::fmt::Display for GridPos {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-#[derive(Component)]
-pub(crate) struct FilledCell {
-    pub(crate) color_from_kind: PieceKind,
-}
-
-#[derive(Component, Clone)]
-pub(crate) struct Fall {
-    pub(crate) down_timer: Timer,
-    pub(crate) lock_timer: Timer,
+/// How the piece moved since it spawned, used for scoring when it locks.
+#[derive(Component, Clone, Copy, Default)]
+pub(crate) struct MoveHistory {
+    /// Last rotation with the index of its kick, cleared when the piece moves
+    /// otherwise
+    pub(crate) last_rotation: Option<(Rotation, usize)>,
+    pub(crate) soft_drop_rows: u32,
+    pub(crate) hard_drop_rows: u32,
 }
 
 #[derive(Bundle, Clone)]
@@ -129,5 +67,6 @@ pub(crate) struct FallingPieceBundle {
     pub(crate) kind: PieceKind,
     pub(crate) pos: GridPos,
     pub(crate) spin: Spin,
+    pub(crate) history: MoveHistory,
     pub(crate) fall: Fall,
 }
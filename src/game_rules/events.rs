@@ -1,6 +1,64 @@
 use bevy::prelude::*;
 
+use tetris_core::board::TSpin;
+use tetris_core::scoring::LineClear;
+
+use super::resources::{GameStats, PlayerInput, Score, Stopwatch, XP};
+
 #[derive(Event, Debug)]
 pub(crate) struct ClearedLines {
     pub(crate) lines_count: u8,
+    pub(crate) t_spin: TSpin,
+    /// Number of clears in a row before this one
+    pub(crate) combo: u32,
+    /// The clear continued a chain of difficult clears
+    pub(crate) back_to_back: bool,
+    pub(crate) perfect_clear: bool,
+}
+
+/// A piece was locked into the grid.
+#[derive(Event, Debug, Clone, Copy)]
+pub(crate) struct PieceLocked {
+    /// Lines completed by the piece
+    pub(crate) clear: LineClear,
+    pub(crate) soft_drop_rows: u32,
+    pub(crate) hard_drop_rows: u32,
+}
+
+/// The stack reached the top of the grid.
+/// See https://tetris.wiki/Top_out
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TopOut {
+    /// A new piece overlaps with locked cells when spawning
+    BlockOut,
+    /// A piece locked entirely above the visible area
+    LockOut,
+}
+
+/// Reason why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GameEnd {
+    TopOut(TopOut),
+    /// The target of the game mode was reached
+    GoalReached,
+    /// The time limit of the game mode ran out
+    TimeUp,
+}
+
+/// Sent once when the game ends, with final statistics of the game.
+#[derive(Event, Debug)]
+pub(crate) struct GameOver {
+    pub(crate) cause: GameEnd,
+    pub(crate) score: Score,
+    pub(crate) xp: XP,
+    pub(crate) stopwatch: Stopwatch,
+    pub(crate) stats: GameStats,
 }
+
+/// Request to discard current game and start a new one.
+#[derive(Event, Debug, Default)]
+pub(crate) struct RestartGame;
+
+/// An input was popped from the queue and applied to the falling piece.
+#[derive(Event, Debug)]
+pub(crate) struct AppliedInput(pub(crate) PlayerInput);
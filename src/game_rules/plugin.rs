@@ -1,7 +1,8 @@
-use bevy::input::keyboard::keyboard_input_system;
 use bevy::prelude::*;
-
-use crate::ui_controls::plugin::UiControlsSystems;
+use bevy::state::app::StatesPlugin;
+use tetris_core::board::BoardConfig;
+use tetris_core::rotation::Kicks180;
+use tetris_core::scoring::{ScoringRules, ScoringState};
 
 use super::events::*;
 use super::resources::*;
@@ -10,35 +11,76 @@ use super::systems::*;
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct GameUpdateSystems;
 
-pub(crate) struct GameRulesPlugin;
+pub(crate) struct GameRulesPlugin {
+    /// Number of simulation steps per second
+    pub(crate) tick_rate: f64,
+}
 
 impl Plugin for GameRulesPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Stopwatch>()
+        // Game rules don't require a window and can run along `MinimalPlugins`
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+
+        app.insert_resource(Time::<Fixed>::from_hz(self.tick_rate))
+            .init_resource::<PlayerInputQueue>()
+            .init_resource::<SoftDrop>()
+            .init_resource::<InstantShift>()
+            .init_resource::<Stopwatch>()
+            .init_resource::<GameStats>()
+            .init_resource::<GameTick>()
+            .init_resource::<RandomizerConfig>()
+            .init_resource::<GameSettings>()
             .init_resource::<PieceGenerator>()
+            .init_resource::<HeldPiece>()
             .init_resource::<Score>()
+            .init_resource::<BoardConfig>()
             .init_resource::<GridState>()
             .init_resource::<XP>()
-            .init_resource::<Events<ClearedLines>>()
+            .init_resource::<Kicks180>()
+            .init_resource::<ScoringRules>()
+            .init_resource::<ScoringState>()
+            .init_state::<AppState>()
+            .add_event::<ClearedLines>()
+            .add_event::<PieceLocked>()
+            .add_event::<TopOut>()
+            .add_event::<GameOver>()
+            .add_event::<RestartGame>()
+            .add_event::<AppliedInput>()
+            .add_systems(OnExit(AppState::Playing), release_held_inputs)
+            // Game rules run on a fixed timestep to be reproducible and independent of
+            // the framerate, rendering then displays the latest simulated state.
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
-                    resume_after_clear.run_if(resource_exists::<PausedForClear>),
+                    restart_game,
+                    resume_after_clear
+                        .run_if(resource_exists::<PausedForClear>)
+                        .run_if(not(resource_exists::<GameEnded>))
+                        .run_if(in_state(AppState::Playing)),
                     (
                         piece_spawn,
-                        piece_move.after(keyboard_input_system),
+                        piece_move,
+                        piece_shift,
                         piece_lock,
                         piece_fall,
                         register_completed_lines,
                         update_score,
                         update_xp,
+                        update_stats,
                         update_stopwatch,
+                        trigger_game_over,
                     )
                         .chain()
                         .run_if(not(resource_exists::<PausedForClear>))
+                        .run_if(not(resource_exists::<GameEnded>))
+                        .run_if(in_state(AppState::Playing))
                         .in_set(GameUpdateSystems),
+                    update_game_tick
+                        .run_if(not(resource_exists::<GameEnded>))
+                        .run_if(in_state(AppState::Playing)),
                 )
-                    .after(UiControlsSystems)
                     .chain(),
             );
     }
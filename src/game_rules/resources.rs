@@ -1,14 +1,21 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Display;
-use std::ops::DerefMut;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 use std::time::Duration;
 
 use bevy::prelude::*;
-use rand::seq::SliceRandom;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{GRID_HEIGHT, GRID_WIDTH};
+use tetris_core::board::{Board, BoardConfig, TSpin};
+use tetris_core::randomizer::{PieceQueue, RandomizerKind};
+use tetris_core::rotation::{Kicks180, Rotation};
+use tetris_core::scoring::{self, LevelGoal, LevelProgress, LineClear};
 
 use super::components::{FilledCell, GridPos, PieceKind, Spin};
 
+pub(crate) use tetris_core::input::PlayerInput;
+
 /// Soft drop's default behavior is to speedup time by a constant factor
 pub(crate) const SOFT_DROP_SPEEDUP: u32 = 3;
 
@@ -19,6 +26,10 @@ pub(crate) const SOFT_DROP_MAX_DELAY: Duration = Duration::from_millis(50);
 /// See https://tetris.fandom.com/wiki/Lock_delay
 pub(crate) const LOCK_DELAY: Duration = Duration::from_millis(500);
 
+/// Number of times moves and rotations can reset the lock delay with
+/// extended placement, counted again from a new lowest row.
+pub(crate) const MAX_LOCK_RESETS: u32 = 15;
+
 /// Duration for which the game pauses when lines are cleared.
 pub(crate) const CLEAR_DELAY: Duration = Duration::from_millis(400);
 
@@ -32,51 +43,252 @@ pub(crate) struct PausedForClear {
     pub(crate) rows_to_delete: Vec<u8>,
 }
 
-// -- Stopwatch
+/// Set when the game is over until it restarts. The `GameOver` state is only
+/// entered after all fixed ticks of a frame, which must not play on.
+#[derive(Resource)]
+pub(crate) struct GameEnded;
+
+// -- AppState
+
+/// Top-level state of the application, game rules only run while playing.
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum AppState {
+    /// No game is running yet
+    Menu,
+    #[default]
+    Playing,
+    /// The game is frozen and hidden behind the pause menu
+    Paused,
+    GameOver,
+}
+
+// -- PlayerInputQueue
 
 #[derive(Resource, Default)]
+pub(crate) struct PlayerInputQueue {
+    pub(crate) queue: VecDeque<PlayerInput>,
+}
+
+impl Deref for PlayerInputQueue {
+    type Target = VecDeque<PlayerInput>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queue
+    }
+}
+
+impl DerefMut for PlayerInputQueue {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.queue
+    }
+}
+
+// -- SoftDrop
+
+/// Gravity is sped up while the player holds the soft drop.
+#[derive(Resource, Default)]
+pub(crate) struct SoftDrop {
+    pub(crate) active: bool,
+}
+
+// -- InstantShift
+
+/// The piece is moved to the wall on every tick, which is used for auto
+/// repeat without any delay.
+#[derive(Resource, Default)]
+pub(crate) struct InstantShift {
+    /// Horizontal direction of the shift
+    pub(crate) dx: Option<i8>,
+}
+
+// -- GameTick
+
+/// Number of simulation steps since the beginning of the game.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct GameTick(pub(crate) u64);
+
+// -- Stopwatch
+
+#[derive(Resource, Clone, Debug)]
 pub(crate) struct Stopwatch {
     pub(crate) since_begining: Duration,
+    /// Display milliseconds, for modes racing against the clock
+    pub(crate) precise: bool,
+    /// Duration of the game, the remaining time is then displayed
+    pub(crate) limit: Option<Duration>,
+}
+
+impl FromWorld for Stopwatch {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource_or_insert_with(GameSettings::default);
+        Self::new(&settings)
+    }
+}
+
+impl Stopwatch {
+    pub(crate) fn new(settings: &GameSettings) -> Self {
+        Self {
+            since_begining: Duration::ZERO,
+            precise: settings.mode.precise_time(),
+            limit: settings.mode.time_limit(),
+        }
+    }
+
+    pub(crate) fn tick(&mut self, delta: Duration) {
+        self.since_begining += delta;
+
+        if let Some(limit) = self.limit {
+            self.since_begining = self.since_begining.min(limit);
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> Option<Duration> {
+        Some(self.limit?.saturating_sub(self.since_begining))
+    }
+
+    pub(crate) fn time_up(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
 }
 
 impl Display for Stopwatch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let hours = self.since_begining.as_secs() / 3600;
-        let minutes = (self.since_begining.as_secs() % 3600) / 60;
-        let seconds = self.since_begining.as_secs() % 60;
+        // Count down when the game is limited in time
+        let time = self.remaining().unwrap_or(self.since_begining);
+        let hours = time.as_secs() / 3600;
+        let minutes = (time.as_secs() % 3600) / 60;
+        let seconds = time.as_secs() % 60;
 
         if hours > 0 {
             write!(f, "{hours:02}")?;
         }
 
-        write!(f, "{minutes:02}:{seconds:02}")
+        write!(f, "{minutes:02}:{seconds:02}")?;
+
+        if self.precise {
+            write!(f, ".{:03}", time.subsec_millis())?;
+        }
+
+        Ok(())
+    }
+}
+
+// -- GameStats
+
+/// What points of the score were awarded for, bonuses of chains and perfect
+/// clears are counted with the clear they were awarded for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ScoreSource {
+    /// Regular clear of given number of lines
+    Lines(u8),
+    TSpinMini(u8),
+    TSpin(u8),
+    /// Soft and hard drops
+    Drops,
+}
+
+impl ScoreSource {
+    pub(crate) fn from_clear(clear: &LineClear) -> Self {
+        match clear.t_spin {
+            TSpin::None => Self::Lines(clear.lines),
+            TSpin::Mini => Self::TSpinMini(clear.lines),
+            TSpin::Full => Self::TSpin(clear.lines),
+        }
+    }
+
+    pub(crate) fn label(self) -> String {
+        let lines = |count| match count {
+            0 => "",
+            1 => " Single",
+            2 => " Double",
+            3 => " Triple",
+            _ => " Tetris",
+        };
+
+        match self {
+            Self::Lines(count) => lines(count).trim_start().to_string(),
+            Self::TSpinMini(count) => format!("T-Spin Mini{}", lines(count)),
+            Self::TSpin(count) => format!("T-Spin{}", lines(count)),
+            Self::Drops => "Drops".to_string(),
+        }
+    }
+}
+
+/// Counters of the current game, used to measure the pace of the player.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct GameStats {
+    /// Number of pieces locked into the grid
+    pub(crate) pieces: u32,
+    /// Number of inputs applied to falling pieces, releases are not counted
+    pub(crate) inputs: u32,
+    /// Breakdown of the score
+    pub(crate) points: BTreeMap<ScoreSource, u64>,
+}
+
+impl GameStats {
+    /// Pieces per second
+    pub(crate) fn pps(&self, stopwatch: &Stopwatch) -> f64 {
+        let secs = stopwatch.since_begining.as_secs_f64();
+
+        if secs == 0.0 {
+            return 0.0;
+        }
+
+        f64::from(self.pieces) / secs
+    }
+
+    /// Keys per piece
+    pub(crate) fn kpp(&self) -> f64 {
+        if self.pieces == 0 {
+            return 0.0;
+        }
+
+        f64::from(self.inputs) / f64::from(self.pieces)
     }
 }
 
 // -- GridState
 
-#[derive(Resource, Default)]
+/// Locked cells of the grid, along with the entities that display them.
+#[derive(Resource)]
 pub(crate) struct GridState {
-    cells: [[Option<Entity>; GRID_HEIGHT as _]; GRID_WIDTH as _],
+    board: Board,
+    cells: Vec<Option<Entity>>,
+}
+
+impl FromWorld for GridState {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(BoardConfig::default);
+        Self::new(*config)
+    }
+}
+
+impl Deref for GridState {
+    type Target = Board;
+
+    fn deref(&self) -> &Self::Target {
+        &self.board
+    }
 }
 
 impl GridState {
-    pub(crate) fn is_empty(&self, pos: &GridPos) -> bool {
-        (0..GRID_WIDTH).contains(&pos.x)
-            && (0..GRID_HEIGHT).contains(&pos.y)
-            && !self.is_filled(pos)
+    pub(crate) fn new(config: BoardConfig) -> Self {
+        Self {
+            board: Board::new(config),
+            cells: vec![None; usize::from(config.width) * usize::from(config.height)],
+        }
     }
 
-    pub(crate) fn is_filled(&self, pos: &GridPos) -> bool {
-        self.get_filled_entity(pos).is_some()
+    fn index(&self, pos: &GridPos) -> Option<usize> {
+        let config = self.board.config();
+
+        config
+            .contains(pos)
+            .then(|| usize::from(pos.x) * usize::from(config.height) + usize::from(pos.y))
     }
 
     pub(crate) fn get_filled_entity(&self, pos: &GridPos) -> Option<&Entity> {
-        self.cells
-            .get(usize::from(pos.x))?
-            .get(usize::from(pos.y))
-            .as_ref()?
-            .as_ref()
+        self.cells[self.index(pos)?].as_ref()
     }
 
     pub(crate) fn spawn_cell(
@@ -95,7 +307,9 @@ impl GridState {
             ))
             .id();
 
-        self.cells[usize::from(pos.x)][usize::from(pos.y)] = Some(entity);
+        self.board.set(pos, Some(color_from_kind));
+        let index = self.index(pos).expect("Position out of the grid");
+        self.cells[index] = Some(entity);
     }
 
     pub(crate) fn despawn_cell(&mut self, commands: &mut Commands, pos: &GridPos) -> bool {
@@ -104,7 +318,9 @@ impl GridState {
         };
 
         commands.entity(entity).despawn();
-        self.cells[usize::from(pos.x)][usize::from(pos.y)] = None;
+        self.board.set(pos, None);
+        let index = self.index(pos).expect("Position out of the grid");
+        self.cells[index] = None;
         true
     }
 
@@ -126,16 +342,22 @@ impl GridState {
             commands.entity(moved_entity).insert(*to);
         };
 
-        self.cells[usize::from(to.x)][usize::from(to.y)] =
-            self.cells[usize::from(from.x)][usize::from(from.y)].take();
+        self.board.set(to, self.board.get(from));
+        self.board.set(from, None);
+
+        let from = self.index(from).expect("Position out of the grid");
+        let to = self.index(to).expect("Position out of the grid");
+        self.cells[to] = self.cells[from].take();
 
         true
     }
 
-    fn conflicts(&self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
-        !kind
-            .piece_covered_cells(pos, spin)
-            .all(|pos| self.is_empty(&pos))
+    // Following methods only write through the references on success, which
+    // keeps change detection of components accurate.
+
+    /// The piece can't fall any further.
+    pub(crate) fn is_grounded(&self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
+        !self.board.try_move([0, -1], kind, &mut pos.clone(), spin)
     }
 
     pub(crate) fn try_move(
@@ -145,12 +367,9 @@ impl GridState {
         mut pos: impl DerefMut<Target = GridPos>,
         spin: Spin,
     ) -> bool {
-        let new_pos = GridPos {
-            x: pos.x.wrapping_add_signed(delta[0]),
-            y: pos.y.wrapping_add_signed(delta[1]),
-        };
+        let mut new_pos = *pos;
 
-        if self.conflicts(kind, new_pos, spin) {
+        if !self.board.try_move(delta, kind, &mut new_pos, spin) {
             return false;
         }
 
@@ -158,68 +377,92 @@ impl GridState {
         true
     }
 
-    fn try_rotate(
+    pub(crate) fn try_rotate(
         &self,
-        delta: Spin,
         kind: PieceKind,
-        mut pos: Mut<GridPos>,
-        mut spin: Mut<Spin>,
-        kick_directions: impl IntoIterator<Item = [i8; 2]>,
-    ) -> bool {
-        let new_spin = Spin((spin.0 + delta.0) % 4);
+        mut pos: impl DerefMut<Target = GridPos>,
+        mut spin: impl DerefMut<Target = Spin>,
+        rotation: Rotation,
+        kicks_180: Kicks180,
+    ) -> Option<usize> {
+        let (mut new_pos, mut new_spin) = (*pos, *spin);
+
+        let kick = self
+            .board
+            .try_rotate(kind, &mut new_pos, &mut new_spin, rotation, kicks_180)?;
+
+        (*pos, *spin) = (new_pos, new_spin);
+        Some(kick)
+    }
+}
 
-        if self.conflicts(kind, *pos, new_spin)
-            && !kick_directions
-                .into_iter()
-                .any(|dir| self.try_move(dir, kind, pos.reborrow(), new_spin))
-        {
-            return false;
-        }
+// -- XP
 
-        *spin = new_spin;
-        true
-    }
+#[derive(Resource, Clone, Debug)]
+pub(crate) struct XP {
+    /// Total number of cleared lines
+    pub(crate) lines: u32,
+    pub(crate) progress: LevelProgress,
+    /// Lines required to level up, the level stays the same otherwise
+    pub(crate) level_goal: Option<LevelGoal>,
+    /// The game is finished once this level is completed
+    pub(crate) last_level: Option<u32>,
+    /// Number of lines to clear to finish the game
+    pub(crate) line_goal: Option<u32>,
+}
 
-    pub(crate) fn try_rotate_right(
-        &self,
-        kind: PieceKind,
-        pos: Mut<GridPos>,
-        spin: Mut<Spin>,
-    ) -> bool {
-        let kick_directions = kind.wall_kick_incr_dirs()[usize::from(spin.0 % 4)];
-        self.try_rotate(Spin(1), kind, pos, spin, kick_directions)
+impl FromWorld for XP {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource_or_insert_with(GameSettings::default);
+        Self::new(&settings)
     }
+}
 
-    pub(crate) fn try_rotate_left(
-        &self,
-        kind: PieceKind,
-        pos: Mut<GridPos>,
-        spin: Mut<Spin>,
-    ) -> bool {
-        let kick_directions = kind.wall_kick_incr_dirs()[usize::from((spin.0 + 3) % 4)]
-            .into_iter()
-            .map(|[x, y]| [-x, -y]);
+impl XP {
+    pub(crate) fn new(settings: &GameSettings) -> Self {
+        let level_goal = settings.mode.level_goal();
+        let start_level = if level_goal.is_some() {
+            settings.start_level
+        } else {
+            1
+        };
 
-        self.try_rotate(Spin(3), kind, pos, spin, kick_directions)
+        Self {
+            lines: 0,
+            progress: LevelProgress::new(start_level),
+            level_goal,
+            last_level: settings.mode.last_level().map(|last| last.max(start_level)),
+            line_goal: settings.mode.line_goal(),
+        }
     }
-}
 
-// -- XP
+    pub(crate) fn register(&mut self, clear: &LineClear, back_to_back: bool) {
+        self.lines += u32::from(clear.lines);
 
-#[derive(Resource, Default)]
-pub(crate) struct XP(pub(crate) u32);
+        if let Some(goal) = self.level_goal {
+            self.progress
+                .award(goal, goal.awarded_lines(clear, back_to_back));
+        }
+    }
 
-impl XP {
     pub(crate) fn level(&self) -> u32 {
-        1 + self.0 / 10
+        // Level keeps the same value once the last one is completed
+        self.progress.level.min(self.last_level.unwrap_or(u32::MAX))
     }
 
-    /// See https://tetris.fandom.com/wiki/Tetris_Worlds#Gravity
     pub(crate) fn time_per_row(&self) -> Duration {
-        Duration::from_secs_f64(
-            (0.8 - (f64::from(self.level() - 1) * 0.007))
-                .powi(i32::try_from(self.level() - 1).expect("Level Overflow")),
-        )
+        scoring::time_per_row(self.level())
+    }
+
+    pub(crate) fn lines_remaining(&self) -> Option<u32> {
+        Some(self.line_goal?.saturating_sub(self.lines))
+    }
+
+    pub(crate) fn goal_reached(&self) -> bool {
+        self.lines_remaining() == Some(0)
+            || self
+                .last_level
+                .is_some_and(|last| self.progress.level > last)
     }
 }
 
@@ -230,7 +473,7 @@ impl Display for XP {
 }
 
 // -- Score
-#[derive(Resource, Default)]
+#[derive(Resource, Clone, Debug, Default)]
 pub(crate) struct Score(pub(crate) u64);
 
 impl Display for Score {
@@ -250,32 +493,284 @@ impl Display for Score {
     }
 }
 
-// -- PieceGenerator
+// -- HeldPiece
 
+/// Piece put aside by the player, it can only be swapped once per spawned
+/// piece.
+/// See https://tetris.wiki/Hold_piece
 #[derive(Resource, Default)]
+pub(crate) struct HeldPiece {
+    pub(crate) kind: Option<PieceKind>,
+    /// Set after a swap, until next piece is spawned
+    pub(crate) locked: bool,
+}
+
+// -- GameSettings
+
+/// Last level of a marathon which is not endless.
+pub(crate) const MARATHON_LEVELS: u32 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum GameMode {
+    /// Gravity increases with levels, until the last level is completed
+    Marathon { goal: LevelGoal, endless: bool },
+    /// Race to clear given number of lines, gravity stays at level 1
+    Sprint(u32),
+    /// Score as much as possible in given number of minutes
+    Ultra(u32),
+}
+
+impl GameMode {
+    /// Line targets that can be picked for a sprint.
+    pub(crate) const SPRINT_LINES: [u32; 3] = [20, 40, 100];
+
+    /// Durations that can be picked for an ultra, in minutes.
+    pub(crate) const ULTRA_MINUTES: [u32; 2] = [2, 3];
+
+    pub(crate) const fn all() -> [Self; 9] {
+        [
+            Self::marathon(LevelGoal::Fixed, false),
+            Self::marathon(LevelGoal::Variable, false),
+            Self::marathon(LevelGoal::Fixed, true),
+            Self::marathon(LevelGoal::Variable, true),
+            Self::Sprint(Self::SPRINT_LINES[0]),
+            Self::Sprint(Self::SPRINT_LINES[1]),
+            Self::Sprint(Self::SPRINT_LINES[2]),
+            Self::Ultra(Self::ULTRA_MINUTES[0]),
+            Self::Ultra(Self::ULTRA_MINUTES[1]),
+        ]
+    }
+
+    pub(crate) fn label(self) -> String {
+        match self {
+            Self::Marathon { goal, endless } => {
+                let goal = match goal {
+                    LevelGoal::Fixed => "",
+                    LevelGoal::Variable => " Var.",
+                };
+
+                let endless = if endless { " Endless" } else { "" };
+                format!("Marathon{goal}{endless}")
+            }
+            Self::Sprint(lines) => format!("Sprint {lines}"),
+            Self::Ultra(minutes) => format!("Ultra {minutes}:00"),
+        }
+    }
+
+    pub(crate) const fn marathon(goal: LevelGoal, endless: bool) -> Self {
+        Self::Marathon { goal, endless }
+    }
+
+    pub(crate) const fn level_goal(self) -> Option<LevelGoal> {
+        match self {
+            Self::Marathon { goal, .. } => Some(goal),
+            Self::Sprint(_) => None,
+            Self::Ultra(_) => Some(LevelGoal::Fixed),
+        }
+    }
+
+    pub(crate) const fn last_level(self) -> Option<u32> {
+        match self {
+            Self::Marathon { endless: false, .. } => Some(MARATHON_LEVELS),
+            _ => None,
+        }
+    }
+
+    pub(crate) const fn line_goal(self) -> Option<u32> {
+        match self {
+            Self::Marathon { .. } | Self::Ultra(_) => None,
+            Self::Sprint(lines) => Some(lines),
+        }
+    }
+
+    pub(crate) const fn time_limit(self) -> Option<Duration> {
+        match self {
+            Self::Marathon { .. } | Self::Sprint(_) => None,
+            Self::Ultra(minutes) => Some(Duration::from_secs(60 * minutes as u64)),
+        }
+    }
+
+    /// The game is played against the clock, which is then displayed with
+    /// milliseconds.
+    pub(crate) const fn precise_time(self) -> bool {
+        matches!(self, Self::Sprint(_) | Self::Ultra(_))
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Marathon { goal, endless } => {
+                write!(f, "marathon")?;
+
+                if *goal == LevelGoal::Variable {
+                    write!(f, "-variable")?;
+                }
+
+                if *endless {
+                    write!(f, "-endless")?;
+                }
+
+                Ok(())
+            }
+            Self::Sprint(lines) => write!(f, "sprint-{lines}"),
+            Self::Ultra(minutes) => write!(f, "ultra-{minutes}"),
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(lines) = s.strip_prefix("sprint-") {
+            return match lines.parse() {
+                Ok(lines) if lines > 0 => Ok(Self::Sprint(lines)),
+                _ => Err(format!("invalid sprint line target `{lines}`")),
+            };
+        }
+
+        if let Some(minutes) = s.strip_prefix("ultra-") {
+            return match minutes.parse() {
+                Ok(minutes) if minutes > 0 => Ok(Self::Ultra(minutes)),
+                _ => Err(format!("invalid ultra duration `{minutes}`")),
+            };
+        }
+
+        Self::all()
+            .into_iter()
+            .find(|mode| mode.to_string() == s)
+            .ok_or_else(|| format!("unknown game mode `{s}`"))
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        Self::marathon(LevelGoal::Fixed, false)
+    }
+}
+
+// Modes are saved under their command line name
+impl Serialize for GameMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Moves that reset the lock delay of a piece lying on the ground.
+/// See https://tetris.wiki/Lock_delay
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum LockDelay {
+    /// Moves and rotations reset the delay, up to `MAX_LOCK_RESETS` times
+    #[default]
+    Extended,
+    /// Moves and rotations always reset the delay
+    Infinite,
+    /// The delay is only reset when the piece reaches a new lowest row
+    Step,
+}
+
+impl LockDelay {
+    pub(crate) const fn all() -> [Self; 3] {
+        [Self::Extended, Self::Infinite, Self::Step]
+    }
+}
+
+impl Display for LockDelay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Extended => write!(f, "extended"),
+            Self::Infinite => write!(f, "infinite"),
+            Self::Step => write!(f, "step"),
+        }
+    }
+}
+
+impl FromStr for LockDelay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|policy| policy.to_string() == s)
+            .ok_or_else(|| format!("unknown lock delay `{s}`"))
+    }
+}
+
+/// Rules of the game, applied when a game starts.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GameSettings {
+    pub(crate) mode: GameMode,
+    pub(crate) start_level: u32,
+    pub(crate) lock_delay: LockDelay,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            start_level: 1,
+            lock_delay: LockDelay::default(),
+        }
+    }
+}
+
+// -- RandomizerConfig
+
+/// Settings used to build the piece generator when a game starts.
+#[derive(Resource, Clone, Debug, Default)]
+pub(crate) struct RandomizerConfig {
+    pub(crate) kind: RandomizerKind,
+    /// Seed shared by every game, a new one is drawn for each game when
+    /// unset
+    pub(crate) seed: Option<u64>,
+}
+
+// -- PieceGenerator
+
+#[derive(Resource)]
 pub(crate) struct PieceGenerator {
-    pending: Vec<PieceKind>,
+    queue: PieceQueue,
+    /// Seed the randomizer was built with
+    pub(crate) seed: u64,
 }
 
 impl PieceGenerator {
-    fn ensure_pending_is_not_empty(&mut self) {
-        if !self.pending.is_empty() {
-            return;
+    pub(crate) fn new(config: &RandomizerConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
+
+        Self {
+            queue: PieceQueue::new(config.kind.build(seed)),
+            seed,
         }
+    }
+}
 
-        let mut rng = rand::thread_rng();
-        let mut pool = PieceKind::all();
-        pool.shuffle(&mut rng);
-        self.pending.extend_from_slice(&pool);
+impl Deref for PieceGenerator {
+    type Target = PieceQueue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queue
     }
+}
 
-    pub(crate) fn choose(&mut self) -> PieceKind {
-        self.ensure_pending_is_not_empty();
-        self.pending.pop().unwrap()
+impl DerefMut for PieceGenerator {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.queue
     }
+}
 
-    pub(crate) fn peek(&mut self) -> PieceKind {
-        self.ensure_pending_is_not_empty();
-        *self.pending.last().unwrap()
+impl FromWorld for PieceGenerator {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(RandomizerConfig::default);
+        Self::new(&config)
     }
 }
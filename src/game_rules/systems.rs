@@ -1,23 +1,70 @@
 use bevy::prelude::*;
 
-use crate::ui_controls::resources::{PlayerInput, PlayerInputQueue};
-use crate::{GRID_VISIBLE_HEIGHT, GRID_WIDTH};
+use tetris_core::board::BoardConfig;
+use tetris_core::rotation::{Kicks180, Rotation};
+use tetris_core::scoring::{LineClear, ScoringRules, ScoringState};
 
 use super::components::*;
 use super::events::*;
 use super::resources::*;
 
-// -- Stopwatch
+// -- Time
+
+pub(crate) fn update_game_tick(mut tick: ResMut<GameTick>) {
+    tick.0 += 1;
+}
 
 pub(crate) fn update_stopwatch(mut stopwatch: ResMut<Stopwatch>, time: Res<Time>) {
-    stopwatch.since_begining += time.delta();
+    stopwatch.tick(time.delta());
 }
 
 // -- Piece movement
 
+/// Spawn a new falling piece at the top of the grid, returns `false` if it
+/// overlaps with filled cells. An initial rotation is dropped if the rotated
+/// piece doesn't fit.
+fn spawn_piece(
+    commands: &mut Commands,
+    grid: &GridState,
+    kind: PieceKind,
+    spin: Spin,
+    xp: &XP,
+) -> bool {
+    let Some((pos, spin)) = [spin, Spin(0)]
+        .into_iter()
+        .find_map(|spin| Some((grid.spawn(kind, spin)?, spin)))
+    else {
+        return false;
+    };
+
+    commands.spawn((
+        Name::new("Falling Piece"),
+        FallingPieceBundle {
+            pos,
+            kind,
+            spin,
+            history: MoveHistory::default(),
+            fall: Fall {
+                down_timer: Timer::new(xp.time_per_row(), TimerMode::Repeating),
+                lock_timer: Timer::new(LOCK_DELAY, TimerMode::Once),
+                lowest_row: pos.y,
+                lock_resets: 0,
+            },
+        },
+    ));
+
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn piece_spawn(
     mut commands: Commands,
     mut piece_generator: ResMut<PieceGenerator>,
+    mut held: ResMut<HeldPiece>,
+    mut top_out: EventWriter<TopOut>,
+    mut applied_inputs: EventWriter<AppliedInput>,
+    mut player_inputs: ResMut<PlayerInputQueue>,
+    grid: Res<GridState>,
     pieces: Query<(), (With<PieceKind>, With<Fall>)>,
     xp: Res<XP>,
 ) {
@@ -25,71 +72,138 @@ pub(crate) fn piece_spawn(
         return;
     }
 
-    let kind = piece_generator.choose();
-    let x = if kind.base_width() % 2 == 0 { 5 } else { 4 };
+    let mut kind = piece_generator.choose();
+    let mut spin = Spin(0);
+    held.locked = false;
+
+    // Initial Rotation and Hold Systems: rotations and holds entered before
+    // the piece appears apply to its spawn.
+    // See https://tetris.wiki/Initial_Rotation_System
+    while let Some(&input) = player_inputs.front() {
+        match input {
+            PlayerInput::RotateRight => spin = Rotation::Clockwise.apply(spin),
+            PlayerInput::RotateLeft => spin = Rotation::CounterClockwise.apply(spin),
+            PlayerInput::Rotate180 => spin = Rotation::Half.apply(spin),
+            PlayerInput::Hold if !held.locked => {
+                kind = held
+                    .kind
+                    .replace(kind)
+                    .unwrap_or_else(|| piece_generator.choose());
+
+                held.locked = true;
+            }
+            _ => break,
+        }
 
-    let y = GRID_VISIBLE_HEIGHT.wrapping_add_signed(
-        -kind
-            .base_shape()
-            .into_iter()
-            .map(|[_, y]| y)
-            .min()
-            .unwrap_or(0),
-    );
+        player_inputs.pop_front();
+        applied_inputs.send(AppliedInput(input));
+    }
 
-    commands.spawn((
-        Name::new("Falling Piece"),
-        FallingPieceBundle {
-            pos: GridPos { x, y },
-            kind,
-            spin: Spin(0),
-            fall: Fall {
-                down_timer: Timer::new(xp.time_per_row(), TimerMode::Repeating),
-                lock_timer: Timer::new(LOCK_DELAY, TimerMode::Once),
-            },
+    if !spawn_piece(&mut commands, &grid, kind, spin, &xp) {
+        top_out.send(TopOut::BlockOut);
+    }
+}
+
+/// Replace a falling piece with filled cells, returns `true` if the piece
+/// was locked entirely above the visible area.
+#[allow(clippy::too_many_arguments)]
+fn lock_piece(
+    commands: &mut Commands,
+    grid: &mut GridState,
+    piece_locked: &mut EventWriter<PieceLocked>,
+    entity: Entity,
+    kind: PieceKind,
+    pos: GridPos,
+    spin: Spin,
+    history: MoveHistory,
+) -> bool {
+    // T-spins are checked before the piece fills its own cells
+    let t_spin = grid.t_spin(kind, pos, spin, history.last_rotation);
+    let mut above_skyline = true;
+
+    for cell in kind.piece_covered_cells(pos, spin) {
+        above_skyline &= cell.y >= grid.config().visible_height;
+        grid.spawn_cell(commands, &cell, kind);
+    }
+
+    piece_locked.send(PieceLocked {
+        clear: LineClear {
+            lines: u8::try_from(grid.completed_rows().len()).unwrap(),
+            t_spin,
+            perfect_clear: grid.is_perfect_clear(),
         },
-    ));
+        soft_drop_rows: history.soft_drop_rows,
+        hard_drop_rows: history.hard_drop_rows,
+    });
+
+    commands.entity(entity).despawn_recursive();
+    above_skyline
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn piece_lock(
     mut grid: ResMut<GridState>,
     mut commands: Commands,
-    mut piece: Query<(Entity, &PieceKind, &GridPos, &Spin, &mut Fall)>,
+    mut top_out: EventWriter<TopOut>,
+    mut piece_locked: EventWriter<PieceLocked>,
+    mut piece: Query<(Entity, &PieceKind, &GridPos, &Spin, &MoveHistory, &mut Fall)>,
+    settings: Res<GameSettings>,
     time: Res<Time>,
 ) {
-    let Ok((entity, &kind, pos, &spin, mut fall)) = piece.get_single_mut() else {
+    let Ok((entity, &kind, &pos, &spin, &history, mut fall)) = piece.get_single_mut() else {
         return;
     };
 
-    // Check if the piece is lying on the ground
-    if grid.try_move([0, -1], kind, &mut pos.clone(), spin) {
-        fall.lock_timer.reset();
+    // Rows reached by gravity are registered as the piece falls, this covers
+    // kicks of the rotations
+    fall.reach_row(pos.y);
+
+    if !grid.is_grounded(kind, pos, spin) {
+        // With step reset, the delay is kept until a new row is reached
+        if settings.lock_delay != LockDelay::Step {
+            fall.lock_timer.reset();
+        }
+
         return;
     }
 
-    fall.lock_timer.tick(time.delta());
-
-    if fall.lock_timer.finished() {
-        for cell in kind.piece_covered_cells(*pos, spin) {
-            grid.spawn_cell(&mut commands, &cell, kind);
+    let lock_now = {
+        if fall.out_of_resets(settings.lock_delay) {
+            true
+        } else {
+            fall.lock_timer.tick(time.delta());
+            fall.lock_timer.finished()
         }
+    };
 
-        commands.entity(entity).despawn_recursive();
+    if lock_now
+        && lock_piece(
+            &mut commands,
+            &mut grid,
+            &mut piece_locked,
+            entity,
+            kind,
+            pos,
+            spin,
+            history,
+        )
+    {
+        top_out.send(TopOut::LockOut);
     }
 }
 
 pub(crate) fn piece_fall(
     grid: Res<GridState>,
-    mut piece: Query<(&PieceKind, &mut GridPos, &Spin, &mut Fall)>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    mut piece: Query<(&PieceKind, &mut GridPos, &Spin, &mut MoveHistory, &mut Fall)>,
+    soft_drop: Res<SoftDrop>,
     time: Res<Time>,
 ) {
-    let Ok((&kind, mut pos, &spin, mut fall)) = piece.get_single_mut() else {
+    let Ok((&kind, mut pos, &spin, mut history, mut fall)) = piece.get_single_mut() else {
         return;
     };
 
     let delta = {
-        if keyboard.any_pressed([KeyCode::ArrowDown]) {
+        if soft_drop.active {
             let min_speedup = (fall.down_timer.duration()).div_duration_f64(SOFT_DROP_MAX_DELAY);
             time.delta()
                 .mul_f64(f64::from(SOFT_DROP_SPEEDUP).max(min_speedup))
@@ -101,69 +215,192 @@ pub(crate) fn piece_fall(
     fall.down_timer.tick(delta);
 
     for _ in 0..fall.down_timer.times_finished_this_tick() {
-        grid.try_move([0, -1], kind, pos.reborrow(), spin);
+        if grid.try_move([0, -1], kind, pos.reborrow(), spin) {
+            history.last_rotation = None;
+            fall.reach_row(pos.y);
+
+            if soft_drop.active {
+                history.soft_drop_rows += 1;
+            }
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn piece_move(
     mut commands: Commands,
+    mut top_out: EventWriter<TopOut>,
+    mut applied_inputs: EventWriter<AppliedInput>,
+    mut piece_locked: EventWriter<PieceLocked>,
     mut player_inputs: ResMut<PlayerInputQueue>,
     mut grid: ResMut<GridState>,
-    mut pieces: Query<(Entity, &PieceKind, &mut GridPos, &mut Spin), With<Fall>>,
+    mut held: ResMut<HeldPiece>,
+    mut piece_generator: ResMut<PieceGenerator>,
+    xp: Res<XP>,
+    kicks_180: Res<Kicks180>,
+    settings: Res<GameSettings>,
+    mut soft_drop: ResMut<SoftDrop>,
+    mut shift: ResMut<InstantShift>,
+    mut pieces: Query<(
+        Entity,
+        &PieceKind,
+        &mut GridPos,
+        &mut Spin,
+        &mut MoveHistory,
+        &mut Fall,
+    )>,
 ) {
-    for (entity, &kind, mut pos, mut spin) in &mut pieces {
+    for (entity, &kind, mut pos, mut spin, mut history, mut fall) in &mut pieces {
         while let Some(input) = player_inputs.pop_front() {
+            applied_inputs.send(AppliedInput(input));
+            let grounded = grid.is_grounded(kind, *pos, *spin);
+
             match input {
-                PlayerInput::MoveLeft => {
-                    grid.try_move([-1, 0], kind, pos.reborrow(), *spin.reborrow());
-                }
-                PlayerInput::MoveRight => {
-                    grid.try_move([1, 0], kind, pos.reborrow(), *spin.reborrow());
+                PlayerInput::MoveLeft | PlayerInput::MoveRight => {
+                    let dx = if input == PlayerInput::MoveLeft {
+                        -1
+                    } else {
+                        1
+                    };
+
+                    if grid.try_move([dx, 0], kind, pos.reborrow(), *spin) {
+                        history.last_rotation = None;
+
+                        if grounded {
+                            fall.grounded_move(settings.lock_delay);
+                        }
+                    }
                 }
                 PlayerInput::HardDrop => {
-                    while grid.try_move([0, -1], kind, pos.reborrow(), *spin) {}
+                    while grid.try_move([0, -1], kind, pos.reborrow(), *spin) {
+                        history.last_rotation = None;
+                        history.hard_drop_rows += 1;
+                    }
 
-                    for cell in kind.piece_covered_cells(*pos.reborrow(), *spin) {
-                        grid.spawn_cell(&mut commands, &cell, kind);
+                    if lock_piece(
+                        &mut commands,
+                        &mut grid,
+                        &mut piece_locked,
+                        entity,
+                        kind,
+                        *pos,
+                        *spin,
+                        *history,
+                    ) {
+                        top_out.send(TopOut::LockOut);
                     }
 
-                    commands.entity(entity).despawn_recursive();
                     break;
                 }
-                PlayerInput::RotateRight => {
-                    grid.try_rotate_right(kind, pos.reborrow(), spin.reborrow());
+                PlayerInput::RotateRight | PlayerInput::RotateLeft | PlayerInput::Rotate180 => {
+                    let rotation = match input {
+                        PlayerInput::RotateRight => Rotation::Clockwise,
+                        PlayerInput::RotateLeft => Rotation::CounterClockwise,
+                        _ => Rotation::Half,
+                    };
+
+                    if let Some(kick) =
+                        grid.try_rotate(kind, pos.reborrow(), spin.reborrow(), rotation, *kicks_180)
+                    {
+                        history.last_rotation = Some((rotation, kick));
+
+                        if grounded {
+                            fall.grounded_move(settings.lock_delay);
+                        }
+                    }
                 }
-                PlayerInput::RotateLeft => {
-                    grid.try_rotate_left(kind, pos.reborrow(), spin.reborrow());
+                PlayerInput::SoftDrop => soft_drop.active = true,
+                PlayerInput::SoftDropRelease => soft_drop.active = false,
+                PlayerInput::ShiftLeft | PlayerInput::ShiftRight => {
+                    let dx = if input == PlayerInput::ShiftLeft {
+                        -1
+                    } else {
+                        1
+                    };
+                    shift.dx = Some(dx);
+
+                    if grid.try_move([dx, 0], kind, pos.reborrow(), *spin) {
+                        if grounded {
+                            fall.grounded_move(settings.lock_delay);
+                        }
+
+                        history.last_rotation = None;
+
+                        while grid.try_move([dx, 0], kind, pos.reborrow(), *spin) {}
+                    }
+                }
+                PlayerInput::ShiftRelease => shift.dx = None,
+                PlayerInput::Hold => {
+                    if held.locked {
+                        continue;
+                    }
+
+                    let new_kind = held
+                        .kind
+                        .replace(kind)
+                        .unwrap_or_else(|| piece_generator.choose());
+
+                    held.locked = true;
+                    commands.entity(entity).despawn_recursive();
+
+                    if !spawn_piece(&mut commands, &grid, new_kind, Spin(0), &xp) {
+                        top_out.send(TopOut::BlockOut);
+                    }
+
+                    break;
                 }
             }
         }
     }
 }
 
-pub(crate) fn register_completed_lines(
-    mut commands: Commands,
-    mut cleared_lines: EventWriter<ClearedLines>,
-    grid: ResMut<GridState>,
+pub(crate) fn piece_shift(
+    grid: Res<GridState>,
+    shift: Res<InstantShift>,
+    settings: Res<GameSettings>,
+    mut piece: Query<(&PieceKind, &mut GridPos, &Spin, &mut MoveHistory, &mut Fall)>,
 ) {
-    if !grid.is_changed() {
+    let Some(dx) = shift.dx else {
+        return;
+    };
+
+    let Ok((&kind, mut pos, &spin, mut history, mut fall)) = piece.get_single_mut() else {
+        return;
+    };
+
+    let grounded = grid.is_grounded(kind, *pos, spin);
+
+    if !grid.try_move([dx, 0], kind, pos.reborrow(), spin) {
         return;
     }
 
-    let mut rows_to_delete = Vec::new();
+    // Shifting to the wall counts as a single move
+    if grounded {
+        fall.grounded_move(settings.lock_delay);
+    }
 
-    for y in 0..GRID_VISIBLE_HEIGHT {
-        if (0..GRID_WIDTH).all(|x| grid.is_filled(&GridPos { x, y })) {
-            rows_to_delete.push(y);
-            continue;
-        }
+    history.last_rotation = None;
+    while grid.try_move([dx, 0], kind, pos.reborrow(), spin) {}
+}
+
+/// Held inputs are released when the game stops, as the player may release
+/// them before it resumes.
+pub(crate) fn release_held_inputs(
+    mut soft_drop: ResMut<SoftDrop>,
+    mut shift: ResMut<InstantShift>,
+) {
+    *soft_drop = SoftDrop::default();
+    *shift = InstantShift::default();
+}
+
+pub(crate) fn register_completed_lines(mut commands: Commands, grid: ResMut<GridState>) {
+    if !grid.is_changed() {
+        return;
     }
 
-    if !rows_to_delete.is_empty() {
-        cleared_lines.send(ClearedLines {
-            lines_count: u8::try_from(rows_to_delete.len()).unwrap(),
-        });
+    let rows_to_delete = grid.completed_rows();
 
+    if !rows_to_delete.is_empty() {
         commands.insert_resource(PausedForClear {
             timer: Timer::new(CLEAR_DELAY, TimerMode::Once),
             rows_to_delete,
@@ -183,14 +420,15 @@ pub(crate) fn resume_after_clear(
         return;
     }
 
+    let BoardConfig { width, height, .. } = *grid.config();
     let mut target_line = 0;
 
-    for y in 0..GRID_VISIBLE_HEIGHT {
+    for y in 0..height {
         if pause.rows_to_delete.contains(&y) {
             continue;
         }
 
-        for x in 0..GRID_WIDTH {
+        for x in 0..width {
             grid.move_to(
                 &mut commands,
                 &GridPos { x, y },
@@ -201,8 +439,8 @@ pub(crate) fn resume_after_clear(
         target_line += 1;
     }
 
-    for y in target_line..GRID_VISIBLE_HEIGHT {
-        for x in 0..GRID_WIDTH {
+    for y in target_line..height {
+        for x in 0..width {
             grid.despawn_cell(&mut commands, &GridPos { x, y });
         }
     }
@@ -213,25 +451,139 @@ pub(crate) fn resume_after_clear(
 // -- Score and Leveling
 
 pub(crate) fn update_score(
-    mut cleared_lines: EventReader<ClearedLines>,
+    mut piece_locked: EventReader<PieceLocked>,
+    mut cleared_lines: EventWriter<ClearedLines>,
+    mut scoring_state: ResMut<ScoringState>,
     mut score: ResMut<Score>,
+    mut stats: ResMut<GameStats>,
+    rules: Res<ScoringRules>,
     xp: Res<XP>,
 ) {
-    for clear in cleared_lines.read() {
-        let base_delta = match clear.lines_count {
-            0 => 0,
-            1 => 40,
-            2 => 100,
-            3 => 300,
-            _ => 1200,
-        };
+    for PieceLocked {
+        clear,
+        soft_drop_rows,
+        hard_drop_rows,
+    } in piece_locked.read()
+    {
+        let award = scoring_state.register(&rules, clear, xp.level());
+        let drop_points = rules.drop_points(*soft_drop_rows, *hard_drop_rows);
+        score.0 += award.points + drop_points;
+
+        for (source, points) in [
+            (ScoreSource::from_clear(clear), award.points),
+            (ScoreSource::Drops, drop_points),
+        ] {
+            if points > 0 {
+                *stats.points.entry(source).or_default() += points;
+            }
+        }
 
-        score.0 += u64::from(xp.level()) * base_delta;
+        if clear.lines > 0 {
+            cleared_lines.send(ClearedLines {
+                lines_count: clear.lines,
+                t_spin: clear.t_spin,
+                combo: award.combo,
+                back_to_back: award.back_to_back,
+                perfect_clear: clear.perfect_clear,
+            });
+        }
     }
 }
 
 pub(crate) fn update_xp(mut cleared_lines: EventReader<ClearedLines>, mut xp: ResMut<XP>) {
     for clear in cleared_lines.read() {
-        xp.0 += u32::from(clear.lines_count);
+        let line_clear = LineClear {
+            lines: clear.lines_count,
+            t_spin: clear.t_spin,
+            perfect_clear: clear.perfect_clear,
+        };
+
+        xp.register(&line_clear, clear.back_to_back);
+    }
+}
+
+pub(crate) fn update_stats(
+    mut piece_locked: EventReader<PieceLocked>,
+    mut applied_inputs: EventReader<AppliedInput>,
+    mut stats: ResMut<GameStats>,
+) {
+    stats.pieces += piece_locked.read().count() as u32;
+
+    for AppliedInput(input) in applied_inputs.read() {
+        if !matches!(
+            input,
+            PlayerInput::SoftDropRelease | PlayerInput::ShiftRelease
+        ) {
+            stats.inputs += 1;
+        }
+    }
+}
+
+// -- Game over and restart
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn trigger_game_over(
+    mut commands: Commands,
+    mut top_out: EventReader<TopOut>,
+    mut game_over: EventWriter<GameOver>,
+    mut next_state: ResMut<NextState<AppState>>,
+    score: Res<Score>,
+    xp: Res<XP>,
+    stopwatch: Res<Stopwatch>,
+    stats: Res<GameStats>,
+) {
+    let cause = match top_out.read().last() {
+        Some(&top_out) => GameEnd::TopOut(top_out),
+        None if xp.goal_reached() => GameEnd::GoalReached,
+        None if stopwatch.time_up() => GameEnd::TimeUp,
+        None => return,
+    };
+
+    info!("Game over: {cause:?}");
+
+    game_over.send(GameOver {
+        cause,
+        score: score.clone(),
+        xp: xp.clone(),
+        stopwatch: stopwatch.clone(),
+        stats: stats.clone(),
+    });
+
+    commands.insert_resource(GameEnded);
+    next_state.set(AppState::GameOver);
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn restart_game(
+    mut commands: Commands,
+    mut restart: EventReader<RestartGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+    randomizer: Res<RandomizerConfig>,
+    settings: Res<GameSettings>,
+    board: Res<BoardConfig>,
+    entities: Query<Entity, Or<(With<FilledCell>, With<Fall>)>>,
+) {
+    if restart.read().count() == 0 {
+        return;
     }
+
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.insert_resource(PieceGenerator::new(&randomizer));
+    commands.remove_resource::<PausedForClear>();
+    commands.remove_resource::<GameEnded>();
+    commands.insert_resource(PlayerInputQueue::default());
+    commands.insert_resource(SoftDrop::default());
+    commands.insert_resource(InstantShift::default());
+    commands.insert_resource(GridState::new(*board));
+    commands.insert_resource(HeldPiece::default());
+    commands.insert_resource(Score::default());
+    commands.insert_resource(ScoringState::default());
+    commands.insert_resource(XP::new(&settings));
+    commands.insert_resource(Stopwatch::new(&settings));
+    commands.insert_resource(GameStats::default());
+    commands.insert_resource(GameTick::default());
+    next_state.set(AppState::Playing);
 }
@@ -0,0 +1,69 @@
+pub(crate) mod plugin;
+pub(crate) mod systems;
+
+use std::io::BufRead;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::game_rules::resources::{PlayerInput, PlayerInputQueue};
+
+/// Drive the game from a text stream, one command per line:
+///  - an input name (eg. `move-left`) is applied during next tick
+///  - `wait [n]` runs `n` ticks without any input (default is 1)
+pub(crate) fn run(app: &mut App, input: impl BufRead) -> AppExit {
+    // Each update simulates exactly one tick
+    let tick_duration = app.world().resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(tick_duration));
+    app.finish();
+    app.cleanup();
+
+    // First update runs startup systems but no fixed update
+    app.update();
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            return AppExit::error();
+        };
+
+        let ticks = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [] => continue,
+            ["wait"] => 1,
+            ["wait", n] => match n.parse() {
+                Ok(n) => n,
+                Err(err) => {
+                    eprintln!("invalid tick count `{n}`: {err}");
+                    continue;
+                }
+            },
+            [input] => match input.parse::<PlayerInput>() {
+                Ok(input) => {
+                    app.world_mut()
+                        .resource_mut::<PlayerInputQueue>()
+                        .push_back(input);
+
+                    1
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    continue;
+                }
+            },
+            _ => {
+                eprintln!("invalid command `{line}`");
+                continue;
+            }
+        };
+
+        for _ in 0..ticks {
+            app.update();
+
+            if let Some(exit) = app.should_exit() {
+                return exit;
+            }
+        }
+    }
+
+    AppExit::Success
+}
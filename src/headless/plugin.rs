@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+use crate::game_rules::plugin::GameUpdateSystems;
+
+use super::systems::*;
+
+/// Print the state of the game on the standard output.
+pub(crate) struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (print_cleared_lines, print_board_on_lock, exit_on_game_over)
+                .chain()
+                .after(GameUpdateSystems),
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use tetris_core::board::TSpin;
+
+use crate::game_rules::components::{FilledCell, GridPos, PieceKind};
+use crate::game_rules::events::{ClearedLines, GameOver};
+use crate::game_rules::resources::{GridState, Score, Stopwatch, XP};
+
+pub(crate) fn print_cleared_lines(mut cleared_lines: EventReader<ClearedLines>) {
+    for clear in cleared_lines.read() {
+        let t_spin = match clear.t_spin {
+            TSpin::None => "",
+            TSpin::Mini => "t-spin mini ",
+            TSpin::Full => "t-spin ",
+        };
+
+        let lines = match clear.lines_count {
+            1 => "single",
+            2 => "double",
+            3 => "triple",
+            _ => "tetris",
+        };
+
+        let mut msg = format!("clear: {t_spin}{lines}");
+
+        if clear.back_to_back {
+            msg += " - back-to-back";
+        }
+
+        if clear.combo > 0 {
+            msg += &format!(" - combo {}", clear.combo);
+        }
+
+        if clear.perfect_clear {
+            msg += " - perfect clear";
+        }
+
+        println!("{msg}");
+    }
+}
+
+pub(crate) fn print_board_on_lock(
+    grid: Res<GridState>,
+    new_cells: Query<(), Added<FilledCell>>,
+    score: Res<Score>,
+    xp: Res<XP>,
+    stopwatch: Res<Stopwatch>,
+) {
+    if new_cells.is_empty() {
+        return;
+    }
+
+    let config = grid.config();
+
+    for y in (0..config.height).rev() {
+        // Rows above the skyline are not framed
+        let border = if y < config.visible_height { '|' } else { ' ' };
+
+        let row: String = (0..config.width)
+            .map(|x| {
+                grid.get(&GridPos { x, y })
+                    .map(PieceKind::as_char)
+                    .unwrap_or('.')
+            })
+            .collect();
+
+        println!("{border}{row}{border}");
+    }
+
+    println!("+{}+", "-".repeat(config.width.into()));
+    println!("score: {} - level: {} - time: {}", *score, *xp, *stopwatch);
+}
+
+pub(crate) fn exit_on_game_over(
+    mut game_over: EventReader<GameOver>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for game_over in game_over.read() {
+        println!(
+            "game over ({:?}) - score: {} - level: {} - time: {} - pieces: {} - pps: {:.2} - kpp: {:.2}",
+            game_over.cause,
+            game_over.score,
+            game_over.xp,
+            game_over.stopwatch,
+            game_over.stats.pieces,
+            game_over.stats.pps(&game_over.stopwatch),
+            game_over.stats.kpp(),
+        );
+
+        exit.send(AppExit::Success);
+    }
+}
@@ -1,27 +1,204 @@
 //! Guidelines : https://harddrop.com/wiki/Tetris_Guideline
 
+pub(crate) mod bot;
 pub(crate) mod common;
 pub(crate) mod game_rules;
+pub(crate) mod headless;
+pub(crate) mod replay;
 pub(crate) mod ui_controls;
 pub(crate) mod ui_grid;
+pub(crate) mod ui_menu;
 pub(crate) mod ui_side;
 
 #[cfg(test)]
 pub(crate) mod tests;
 
+use std::time::Duration;
+
 use bevy::ecs::schedule::{LogLevel, ScheduleBuildSettings};
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
+use tetris_core::board::BoardConfig;
+use tetris_core::bot::Weights;
+use tetris_core::randomizer::RandomizerKind;
+use tetris_core::rotation::Kicks180;
+
+use crate::bot::resources::BotPlayer;
+use crate::game_rules::plugin::GameRulesPlugin;
+use crate::game_rules::resources::{GameMode, GameSettings, LockDelay, RandomizerConfig};
+use crate::replay::plugin::{ReplayMode, ReplayPlugin};
+use crate::replay::resources::Replay;
+use crate::ui_controls::resources::AutoShiftConfig;
 
 const WINDOW_TITLE: &str = "Tetris (Bevy Engine)";
 const WINDOW_CLASS: &str = "org.remi-dupre.testing";
 const WINDOW_SIZE: [f32; 2] = [580., 800.];
 
-const GRID_WIDTH: u8 = 10;
-const GRID_HEIGHT: u8 = 22;
-const GRID_VISIBLE_HEIGHT: u8 = 20;
+/// Game ticks per second, guideline's timings are given in frames at 60Hz.
+const TICK_RATE: f64 = 60.0;
+
+const USAGE: &str = "\
+Usage: tetris [OPTIONS]
+
+Options:
+  --seed <u64>         Seed of the piece randomizer
+  --randomizer <name>  One of 7-bag, 14-bag, random, history-4 or scripted:<pieces>
+  --kicks-180 <name>   Kick table of 180° rotations, one of tetrio or none
+  --board <w>x<h>      Width and visible height of the board, 10x20 by default
+  --mode <name>        Game mode, marathon[-variable][-endless], sprint-<lines>
+                       or ultra-<minutes>
+  --level <n>          Starting level
+  --lock-delay <name>  Resets of the lock delay, one of extended, infinite or step
+  --das <ms>           Delay before a held direction repeats
+  --arr <ms>           Delay between repeated moves, 0 moves to the wall
+  --dcd <ms>           Pause of auto-repeat after a rotation, hold or drop
+  --das-priority <p>   Direction to move when both are held: last, first or cancel
+  --bot                Let the bot play, it can be toggled with the B key
+  --bot-weights <w>    Weights of the bot's evaluation, eg. holes=-0.4,lines=1
+                       with holes, bumpiness, height, wells and lines
+  --bot-delay <ms>     Delay between inputs of the bot, 100 by default and 0
+                       to play as fast as possible
+  --record <file>      Save played games into replay files, games after the
+                       first one are numbered (eg. game-2.replay)
+  --replay <file>      Play a game from a replay file
+  --headless           Run without a window, reading inputs from stdin";
+
+/// Command line options, which are all optional.
+#[derive(Default)]
+struct Args {
+    seed: Option<u64>,
+    randomizer: Option<RandomizerKind>,
+    kicks_180: Option<Kicks180>,
+    board: Option<BoardConfig>,
+    mode: Option<GameMode>,
+    level: Option<u32>,
+    lock_delay: Option<LockDelay>,
+    auto_shift: AutoShiftConfig,
+    bot: bool,
+    bot_weights: Weights,
+    bot_delay: Option<Duration>,
+    replay: Option<ReplayMode>,
+    headless: bool,
+}
+
+fn parse_millis(value: &str) -> Result<Duration, String> {
+    let millis = value
+        .parse()
+        .map_err(|_| format!("invalid duration `{value}`"))?;
+
+    Ok(Duration::from_millis(millis))
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut res = Self::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+            match arg.as_str() {
+                "--seed" => {
+                    let value = value()?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed `{value}`"))?;
+                    res.seed = Some(seed);
+                }
+                "--randomizer" => res.randomizer = Some(value()?.parse()?),
+                "--kicks-180" => res.kicks_180 = Some(value()?.parse()?),
+                "--board" => res.board = Some(value()?.parse()?),
+                "--mode" => res.mode = Some(value()?.parse()?),
+                "--level" => {
+                    let value = value()?;
+
+                    let level = value
+                        .parse()
+                        .ok()
+                        .filter(|&level| level >= 1)
+                        .ok_or(format!("invalid level `{value}`"))?;
+
+                    res.level = Some(level);
+                }
+                "--lock-delay" => res.lock_delay = Some(value()?.parse()?),
+                "--das" => res.auto_shift.das = parse_millis(&value()?)?,
+                "--arr" => res.auto_shift.arr = parse_millis(&value()?)?,
+                "--dcd" => res.auto_shift.dcd = parse_millis(&value()?)?,
+                "--das-priority" => res.auto_shift.opposite = value()?.parse()?,
+                "--bot" => res.bot = true,
+                "--bot-weights" => res.bot_weights = value()?.parse()?,
+                "--bot-delay" => res.bot_delay = Some(parse_millis(&value()?)?),
+                "--record" => res.replay = Some(ReplayMode::Record(value()?.into())),
+                "--replay" => {
+                    let path = value()?;
+
+                    let replay = std::fs::File::open(&path)
+                        .and_then(|file| Replay::read(std::io::BufReader::new(file)))
+                        .map_err(|err| format!("could not read replay `{path}`: {err}"))?;
+
+                    res.replay = Some(ReplayMode::Playback(replay));
+                }
+                "--headless" => res.headless = true,
+                _ => return Err(format!("unknown argument `{arg}`")),
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn tick_rate(&self) -> f64 {
+        match &self.replay {
+            Some(ReplayMode::Playback(replay)) => replay.tick_rate,
+            _ => TICK_RATE,
+        }
+    }
+
+    fn kicks_180(&self) -> Kicks180 {
+        match &self.replay {
+            Some(ReplayMode::Playback(replay)) => replay.kicks_180,
+            _ => self.kicks_180.unwrap_or_default(),
+        }
+    }
+
+    fn board_config(&self) -> BoardConfig {
+        match &self.replay {
+            Some(ReplayMode::Playback(replay)) => replay.board,
+            _ => self.board.unwrap_or_default(),
+        }
+    }
+
+    fn game_settings(&self) -> GameSettings {
+        if let Some(ReplayMode::Playback(replay)) = &self.replay {
+            return replay.settings.clone();
+        }
+
+        let default = GameSettings::default();
+
+        GameSettings {
+            mode: self.mode.unwrap_or(default.mode),
+            start_level: self.level.unwrap_or(default.start_level),
+            lock_delay: self.lock_delay.unwrap_or(default.lock_delay),
+        }
+    }
+
+    fn randomizer_config(&self) -> RandomizerConfig {
+        if let Some(ReplayMode::Playback(replay)) = &self.replay {
+            return replay.randomizer_config();
+        }
+
+        RandomizerConfig {
+            kind: self.randomizer.clone().unwrap_or_default(),
+            seed: self.seed,
+        }
+    }
+}
+
+fn main() -> AppExit {
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
 
-fn main() {
     let canvas = {
         if cfg!(debug_assertions) {
             None
@@ -30,41 +207,84 @@ fn main() {
         }
     };
 
-    App::new()
-        // .add_plugins((
-        //     bevy::diagnostic::EntityCountDiagnosticsPlugin,
-        //     bevy::diagnostic::FrameTimeDiagnosticsPlugin,
-        //     bevy::diagnostic::LogDiagnosticsPlugin::default(),
-        // ))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: WINDOW_TITLE.to_string(),
-                name: Some(WINDOW_CLASS.to_string()),
-                resolution: WindowResolution::new(WINDOW_SIZE[0], WINDOW_SIZE[1]),
-                fit_canvas_to_parent: true,
-                canvas,
+    let mut app = App::new();
+    app.insert_resource(args.randomizer_config())
+        .insert_resource(args.game_settings())
+        .insert_resource(args.kicks_180())
+        .insert_resource(args.board_config());
+
+    if args.headless {
+        app.add_plugins(MinimalPlugins);
+    } else {
+        app
+            // .add_plugins((
+            //     bevy::diagnostic::EntityCountDiagnosticsPlugin,
+            //     bevy::diagnostic::FrameTimeDiagnosticsPlugin,
+            //     bevy::diagnostic::LogDiagnosticsPlugin::default(),
+            // ))
+            .add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: WINDOW_TITLE.to_string(),
+                    name: Some(WINDOW_CLASS.to_string()),
+                    resolution: WindowResolution::new(WINDOW_SIZE[0], WINDOW_SIZE[1]),
+                    fit_canvas_to_parent: true,
+                    canvas,
+                    ..Default::default()
+                }),
                 ..Default::default()
-            }),
-            ..Default::default()
-        }))
-        .add_plugins((
-            common::plugin::CommonPlugin,
-            game_rules::plugin::GameRulesPlugin,
-            ui_controls::plugin::UiControlsPlugin,
-            ui_grid::plugin::UiGridPlugin {
-                pos: [-95.0, 0.0], // x: -290..110 ; y: -400..400
-                size: [400.0, 800.0],
-            },
-            ui_side::plugin::UiSidePlugin {
-                pos: [195.0, 0.0], // x: 90..290 ; y: -400..400
-                size: [200.0, 800.0],
-            },
-        ))
-        .edit_schedule(Update, |schedule| {
-            schedule.set_build_settings(ScheduleBuildSettings {
-                ambiguity_detection: LogLevel::Warn,
-                ..default()
-            });
-        })
-        .run();
+            }));
+    }
+
+    app.add_plugins(GameRulesPlugin {
+        tick_rate: args.tick_rate(),
+    })
+    .edit_schedule(FixedUpdate, |schedule| {
+        schedule.set_build_settings(ScheduleBuildSettings {
+            ambiguity_detection: LogLevel::Warn,
+            ..default()
+        });
+    });
+
+    let playback = matches!(args.replay, Some(ReplayMode::Playback(_)));
+
+    if let Some(mode) = args.replay {
+        app.add_plugins(ReplayPlugin { mode });
+    }
+
+    app.add_plugins(bot::plugin::BotPlugin {
+        enabled: args.bot,
+        weights: args.bot_weights,
+        delay: args.bot_delay.unwrap_or(BotPlayer::DEFAULT_DELAY),
+    });
+
+    if args.headless {
+        app.add_plugins(headless::plugin::HeadlessPlugin);
+        return headless::run(&mut app, std::io::stdin().lock());
+    }
+
+    app.add_plugins((
+        common::plugin::CommonPlugin,
+        ui_controls::plugin::UiControlsPlugin {
+            auto_shift: args.auto_shift,
+        },
+        ui_grid::plugin::UiGridPlugin {
+            pos: [-95.0, 0.0], // x: -290..110 ; y: -400..400
+            size: [400.0, 800.0],
+        },
+        ui_side::plugin::UiSidePlugin {
+            pos: [195.0, 0.0], // x: 90..290 ; y: -400..400
+            size: [200.0, 800.0],
+            previews: 5,
+        },
+        ui_menu::plugin::UiMenuPlugin {
+            open_at_startup: !playback,
+        },
+    ))
+    .edit_schedule(Update, |schedule| {
+        schedule.set_build_settings(ScheduleBuildSettings {
+            ambiguity_detection: LogLevel::Warn,
+            ..default()
+        });
+    })
+    .run()
 }
@@ -0,0 +1,3 @@
+pub(crate) mod plugin;
+pub(crate) mod resources;
+pub(crate) mod systems;
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use tetris_core::board::BoardConfig;
+use tetris_core::randomizer::RandomizerKind;
+use tetris_core::rotation::Kicks180;
+
+use crate::game_rules::plugin::GameUpdateSystems;
+use crate::game_rules::resources::{AppState, GameSettings};
+use crate::game_rules::systems::{restart_game, update_game_tick};
+
+use super::resources::*;
+use super::systems::*;
+
+pub(crate) enum ReplayMode {
+    /// Save played games to given file
+    Record(PathBuf),
+    /// Replace player's inputs with a recorded game
+    Playback(Replay),
+}
+
+pub(crate) struct ReplayPlugin {
+    pub(crate) mode: ReplayMode,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match &self.mode {
+            ReplayMode::Record(path) => {
+                app.insert_resource(ReplayRecorder::new(
+                    path.clone(),
+                    Replay::new(
+                        0.0,
+                        RandomizerKind::default(),
+                        0,
+                        Kicks180::default(),
+                        BoardConfig::default(),
+                        GameSettings::default(),
+                    ),
+                ))
+                .add_systems(
+                    FixedUpdate,
+                    record_actions
+                        .after(GameUpdateSystems)
+                        .before(update_game_tick)
+                        .run_if(in_state(AppState::Playing)),
+                )
+                .add_systems(Last, save_recording);
+            }
+            ReplayMode::Playback(replay) => {
+                app.insert_resource(ReplayPlayback {
+                    replay: replay.clone(),
+                    cursor: 0,
+                })
+                .add_systems(
+                    FixedUpdate,
+                    play_actions
+                        .after(restart_game)
+                        .before(GameUpdateSystems)
+                        .run_if(in_state(AppState::Playing)),
+                );
+            }
+        }
+    }
+}
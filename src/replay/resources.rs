@@ -0,0 +1,230 @@
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use tetris_core::board::BoardConfig;
+use tetris_core::randomizer::RandomizerKind;
+use tetris_core::rotation::Kicks180;
+
+use crate::game_rules::resources::{GameSettings, PlayerInput, RandomizerConfig};
+
+/// Header of replay files, followed by the format version.
+pub(crate) const REPLAY_MAGIC: &str = "tetris-replay";
+
+/// Version of the replay format, must be increased on any incompatible
+/// change of the format or of the game rules.
+pub(crate) const REPLAY_VERSION: u32 = 1;
+
+// -- Replay
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ReplayFrame {
+    /// Game tick during which the input was applied
+    pub(crate) tick: u64,
+    pub(crate) input: PlayerInput,
+}
+
+/// Everything needed to reproduce a game.
+///
+/// Replays are stored as text, with a header followed by one action per line:
+///
+/// ```text
+/// tetris-replay 1
+/// tick-rate 60
+/// randomizer 7-bag
+/// seed 42
+/// kicks-180 tetrio
+/// board 10x20
+/// mode marathon
+/// level 1
+/// lock-delay extended
+/// 12 input move-left
+/// 30 input soft-drop
+/// ```
+#[derive(Clone, Debug)]
+pub(crate) struct Replay {
+    /// Number of game ticks per second
+    pub(crate) tick_rate: f64,
+    pub(crate) randomizer: RandomizerKind,
+    /// Seed the randomizer was built with
+    pub(crate) seed: u64,
+    pub(crate) kicks_180: Kicks180,
+    pub(crate) board: BoardConfig,
+    pub(crate) settings: GameSettings,
+    pub(crate) frames: Vec<ReplayFrame>,
+}
+
+fn invalid_data(line: usize, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {msg}"))
+}
+
+impl Replay {
+    pub(crate) fn new(
+        tick_rate: f64,
+        randomizer: RandomizerKind,
+        seed: u64,
+        kicks_180: Kicks180,
+        board: BoardConfig,
+        settings: GameSettings,
+    ) -> Self {
+        Self {
+            tick_rate,
+            randomizer,
+            seed,
+            kicks_180,
+            board,
+            settings,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Randomizer settings which generate the recorded sequence of pieces.
+    pub(crate) fn randomizer_config(&self) -> RandomizerConfig {
+        RandomizerConfig {
+            kind: self.randomizer.clone(),
+            seed: Some(self.seed),
+        }
+    }
+
+    pub(crate) fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{REPLAY_MAGIC} {REPLAY_VERSION}")?;
+        writeln!(writer, "tick-rate {}", self.tick_rate)?;
+        writeln!(writer, "randomizer {}", self.randomizer)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "kicks-180 {}", self.kicks_180)?;
+        writeln!(writer, "board {}", self.board)?;
+        writeln!(writer, "mode {}", self.settings.mode)?;
+        writeln!(writer, "level {}", self.settings.start_level)?;
+        writeln!(writer, "lock-delay {}", self.settings.lock_delay)?;
+
+        for frame in &self.frames {
+            writeln!(writer, "{} input {}", frame.tick, frame.input)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        let mut header = |key: &str| -> io::Result<String> {
+            let (i, line) = lines
+                .next()
+                .ok_or_else(|| invalid_data(0, "unexpected end of file"))?;
+
+            line?
+                .strip_prefix(key)
+                .and_then(|val| val.strip_prefix(' '))
+                .map(str::to_string)
+                .ok_or_else(|| invalid_data(i, format!("expected `{key}`")))
+        };
+
+        let version = header(REPLAY_MAGIC)?;
+
+        if version != REPLAY_VERSION.to_string() {
+            return Err(invalid_data(1, format!("unsupported version {version}")));
+        }
+
+        let tick_rate = header("tick-rate")?
+            .parse()
+            .map_err(|err| invalid_data(2, err))?;
+
+        let randomizer = header("randomizer")?
+            .parse()
+            .map_err(|err| invalid_data(3, err))?;
+
+        let seed = header("seed")?
+            .parse()
+            .map_err(|err| invalid_data(4, err))?;
+
+        let kicks_180 = header("kicks-180")?
+            .parse()
+            .map_err(|err| invalid_data(5, err))?;
+
+        let board = header("board")?
+            .parse()
+            .map_err(|err| invalid_data(6, err))?;
+
+        let settings = GameSettings {
+            mode: header("mode")?
+                .parse()
+                .map_err(|err| invalid_data(7, err))?,
+            start_level: header("level")?
+                .parse()
+                .map_err(|err| invalid_data(8, err))?,
+            lock_delay: header("lock-delay")?
+                .parse()
+                .map_err(|err| invalid_data(9, err))?,
+        };
+
+        let mut replay = Self::new(tick_rate, randomizer, seed, kicks_180, board, settings);
+
+        for (i, line) in lines {
+            let line = line?;
+
+            let (tick, input) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [] => continue,
+                [tick, "input", input] => (tick, input),
+                _ => return Err(invalid_data(i, format!("invalid action `{line}`"))),
+            };
+
+            let tick = tick.parse().map_err(|err| invalid_data(i, err))?;
+            let input = input.parse().map_err(|err| invalid_data(i, err))?;
+            replay.frames.push(ReplayFrame { tick, input });
+        }
+
+        Ok(replay)
+    }
+}
+
+// -- ReplayRecorder
+
+/// Record the games that are played, each one into its own file.
+#[derive(Resource)]
+pub(crate) struct ReplayRecorder {
+    pub(crate) path: PathBuf,
+    pub(crate) replay: Replay,
+    /// Number of games started so far
+    pub(crate) games: u32,
+    /// The current game was saved already
+    pub(crate) saved: bool,
+}
+
+impl ReplayRecorder {
+    pub(crate) fn new(path: PathBuf, replay: Replay) -> Self {
+        Self {
+            path,
+            replay,
+            games: 0,
+            saved: false,
+        }
+    }
+
+    /// File of the current game, games after the first one are numbered
+    /// (eg. `game-2.replay`).
+    pub(crate) fn game_path(&self) -> PathBuf {
+        if self.games <= 1 {
+            return self.path.clone();
+        }
+
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+
+        let name = match self.path.extension() {
+            Some(ext) => format!("{stem}-{}.{}", self.games, ext.to_string_lossy()),
+            None => format!("{stem}-{}", self.games),
+        };
+
+        self.path.with_file_name(name)
+    }
+}
+
+// -- ReplayPlayback
+
+/// Replace player's inputs with the ones from a replay.
+#[derive(Resource)]
+pub(crate) struct ReplayPlayback {
+    pub(crate) replay: Replay,
+    /// Index of the next frame to play
+    pub(crate) cursor: usize,
+}
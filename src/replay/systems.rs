@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use bevy::prelude::*;
+use tetris_core::board::BoardConfig;
+use tetris_core::rotation::Kicks180;
+
+use crate::game_rules::events::{AppliedInput, GameOver};
+use crate::game_rules::resources::{
+    GameSettings, GameTick, PieceGenerator, PlayerInputQueue, RandomizerConfig, Stopwatch, XP,
+};
+
+use super::resources::*;
+
+// -- Recording
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_actions(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut applied_inputs: EventReader<AppliedInput>,
+    randomizer: Res<RandomizerConfig>,
+    piece_generator: Res<PieceGenerator>,
+    kicks_180: Res<Kicks180>,
+    board: Res<BoardConfig>,
+    settings: Res<GameSettings>,
+    tick: Res<GameTick>,
+    time: Res<Time<Fixed>>,
+) {
+    if tick.0 == 0 {
+        // A game restarted before it ended is kept as well
+        save_game(&mut recorder);
+
+        let tick_rate = 1.0 / time.timestep().as_secs_f64();
+        recorder.games += 1;
+        recorder.saved = false;
+
+        recorder.replay = Replay::new(
+            tick_rate,
+            randomizer.kind.clone(),
+            piece_generator.seed,
+            *kicks_180,
+            *board,
+            settings.clone(),
+        );
+    }
+
+    for &AppliedInput(input) in applied_inputs.read() {
+        recorder.replay.frames.push(ReplayFrame {
+            tick: tick.0,
+            input,
+        });
+    }
+}
+
+/// Write the current game to its file, once.
+fn save_game(recorder: &mut ReplayRecorder) {
+    if recorder.games == 0 || recorder.saved {
+        return;
+    }
+
+    let path = recorder.game_path();
+    let res = File::create(&path).and_then(|file| recorder.replay.write(BufWriter::new(file)));
+    recorder.saved = true;
+
+    match res {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(err) => error!("Could not save replay to {}: {err}", path.display()),
+    }
+}
+
+pub(crate) fn save_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut game_over: EventReader<GameOver>,
+    mut exit: EventReader<AppExit>,
+) {
+    if game_over.read().count() + exit.read().count() == 0 {
+        return;
+    }
+
+    save_game(&mut recorder);
+}
+
+// -- Playback
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn play_actions(
+    mut playback: ResMut<ReplayPlayback>,
+    mut player_inputs: ResMut<PlayerInputQueue>,
+    mut randomizer: ResMut<RandomizerConfig>,
+    mut piece_generator: ResMut<PieceGenerator>,
+    mut kicks_180: ResMut<Kicks180>,
+    mut settings: ResMut<GameSettings>,
+    mut xp: ResMut<XP>,
+    mut stopwatch: ResMut<Stopwatch>,
+    tick: Res<GameTick>,
+) {
+    if tick.0 == 0 {
+        // (Re)start the replay with the recorded sequence of pieces
+        playback.cursor = 0;
+        *randomizer = playback.replay.randomizer_config();
+        *piece_generator = PieceGenerator::new(&randomizer);
+        *kicks_180 = playback.replay.kicks_180;
+        *settings = playback.replay.settings.clone();
+        *xp = XP::new(&settings);
+        *stopwatch = Stopwatch::new(&settings);
+    }
+
+    // Live inputs are ignored during a replay
+    player_inputs.clear();
+
+    while let Some(frame) = playback.replay.frames.get(playback.cursor).copied() {
+        if frame.tick > tick.0 {
+            break;
+        }
+
+        player_inputs.push_back(frame.input);
+        playback.cursor += 1;
+    }
+}
@@ -1,4 +1,57 @@
-use crate::game_rules::resources::Score;
+use std::time::Duration;
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::input::gamepad::{
+    GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent,
+    GamepadEvent, GamepadInfo,
+};
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::input::{ButtonState, InputPlugin};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use tetris_core::board::{BoardConfig, TSpin};
+use tetris_core::bot::Weights;
+use tetris_core::rotation::Kicks180;
+use tetris_core::scoring::{LineClear, ScoringState};
+
+use crate::bot::plugin::BotPlugin;
+use crate::bot::resources::BotPlayer;
+use crate::game_rules::components::{Fall, FilledCell, GridPos, MoveHistory, PieceKind, Spin};
+use crate::game_rules::events::{GameEnd, GameOver, RestartGame, TopOut};
+use crate::game_rules::plugin::GameRulesPlugin;
+use crate::game_rules::resources::{
+    AppState, GameMode, GameSettings, GameStats, GameTick, GridState, HeldPiece, LockDelay,
+    PieceGenerator, PlayerInput, PlayerInputQueue, RandomizerConfig, Score, ScoreSource, SoftDrop,
+    Stopwatch, MAX_LOCK_RESETS, XP,
+};
+use crate::headless::plugin::HeadlessPlugin;
+use crate::replay::plugin::{ReplayMode, ReplayPlugin};
+use crate::replay::resources::{Replay, ReplayFrame};
+use crate::ui_controls::resources::{
+    Action, AutoShiftConfig, AutoShiftState, GamepadBindings, GamepadMapping, KeyBindings,
+    KeyBindingsScreen, OppositeDirection,
+};
+use crate::ui_controls::systems::{
+    auto_shift, collect_gamepad_presses, collect_keyboard_presses, reset_auto_shift,
+};
+use crate::ui_menu::resources::BestScores;
+
+fn headless_app(randomizer: &str) -> App {
+    let mut app = App::new();
+
+    app.insert_resource(RandomizerConfig {
+        kind: randomizer.parse().unwrap(),
+        seed: Some(0),
+    })
+    .add_plugins((
+        MinimalPlugins,
+        GameRulesPlugin { tick_rate: 60.0 },
+        HeadlessPlugin,
+    ));
+
+    app
+}
 
 #[test]
 fn test_score_display() {
@@ -13,3 +66,1062 @@ fn test_score_display() {
     assert_eq!(Score(999999).to_string(), "999,999");
     assert_eq!(Score(1000000).to_string(), "1,000,000");
 }
+
+#[test]
+fn test_replay_file_format() {
+    let mut replay = Replay::new(
+        60.0,
+        "scripted:TSZ".parse().unwrap(),
+        1234,
+        Kicks180::NoKicks,
+        "4x40".parse().unwrap(),
+        GameSettings {
+            mode: GameMode::default(),
+            start_level: 3,
+            ..Default::default()
+        },
+    );
+
+    replay.frames = vec![
+        ReplayFrame {
+            tick: 3,
+            input: PlayerInput::MoveLeft,
+        },
+        ReplayFrame {
+            tick: 3,
+            input: PlayerInput::SoftDrop,
+        },
+        ReplayFrame {
+            tick: 60,
+            input: PlayerInput::Hold,
+        },
+    ];
+
+    let mut buffer = Vec::new();
+    replay.write(&mut buffer).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer.clone()).unwrap(),
+        "tetris-replay 1\n\
+         tick-rate 60\n\
+         randomizer scripted:TSZ\n\
+         seed 1234\n\
+         kicks-180 none\n\
+         board 4x40\n\
+         mode marathon\n\
+         level 3\n\
+         lock-delay extended\n\
+         3 input move-left\n\
+         3 input soft-drop\n\
+         60 input hold\n",
+    );
+
+    let parsed = Replay::read(buffer.as_slice()).unwrap();
+    assert_eq!(parsed.tick_rate, replay.tick_rate);
+    assert_eq!(parsed.randomizer, replay.randomizer);
+    assert_eq!(parsed.seed, replay.seed);
+    assert_eq!(parsed.kicks_180, replay.kicks_180);
+    assert_eq!(parsed.board, replay.board);
+    assert_eq!(parsed.settings, replay.settings);
+    assert_eq!(parsed.frames, replay.frames);
+
+    assert!(Replay::read("tetris-replay 0\n".as_bytes()).is_err());
+    assert!(Replay::read(
+        "tetris-replay 1\ntick-rate 60\nrandomizer 7-bag\nseed 1\nkicks-180 tetrio\nboard 10x20\nmode marathon\nlevel 1\nlock-delay step\n4 input fly"
+            .as_bytes()
+    )
+    .is_err());
+}
+
+#[test]
+fn test_tick_rate_gravity() {
+    // Gravity moves the piece by the same rows after the same time, whatever
+    // the number of ticks per second
+    let fall = |tick_rate: f64, secs: f64| {
+        let mut app = App::new();
+        app.insert_resource(RandomizerConfig {
+            kind: "scripted:T".parse().unwrap(),
+            seed: Some(0),
+        })
+        .add_plugins((
+            MinimalPlugins,
+            GameRulesPlugin { tick_rate },
+            HeadlessPlugin,
+        ));
+
+        let ticks = (tick_rate * secs).round();
+        crate::headless::run(&mut app, format!("wait {ticks}\n").as_bytes());
+
+        let world = app.world_mut();
+        let pos = *world.query_filtered::<&GridPos, With<Fall>>().single(world);
+        let elapsed = world.resource::<Stopwatch>().to_string();
+        (pos, elapsed)
+    };
+
+    let mut previous_row = u8::MAX;
+
+    for secs in [0.5, 1.5, 2.5, 4.5] {
+        let expected = fall(60.0, secs);
+        assert!(expected.0.y < previous_row);
+        previous_row = expected.0.y;
+
+        for tick_rate in [30.0, 50.0, 120.0, 240.0] {
+            assert_eq!(fall(tick_rate, secs), expected, "{tick_rate} Hz, {secs}s");
+        }
+    }
+}
+
+#[test]
+fn test_replay_recording() {
+    let dir = std::env::temp_dir().join(format!("tetris-replays-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut app = headless_app("scripted:O");
+    app.add_plugins(ReplayPlugin {
+        mode: ReplayMode::Record(dir.join("game.replay")),
+    });
+
+    // Each game is saved to its own file, including the one left unfinished
+    crate::headless::run(&mut app, "hard-drop\nwait\n".as_bytes());
+    app.world_mut().send_event(RestartGame);
+    crate::headless::run(&mut app, "wait\nmove-left\nhard-drop\n".as_bytes());
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+
+    let read = |name: &str| {
+        let file = std::fs::File::open(dir.join(name)).unwrap();
+        let replay = Replay::read(std::io::BufReader::new(file)).unwrap();
+        replay
+            .frames
+            .into_iter()
+            .map(|frame| frame.input)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(read("game.replay"), [PlayerInput::HardDrop]);
+    assert_eq!(
+        read("game-2.replay"),
+        [PlayerInput::MoveLeft, PlayerInput::HardDrop]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_headless_block_out_and_restart() {
+    let mut app = headless_app("scripted:T");
+
+    // Take a cell of the spawn area before the first piece appears
+    app.world_mut()
+        .run_system_once(|mut commands: Commands, mut grid: ResMut<GridState>| {
+            let pos = grid.config().spawn_position(PieceKind::T);
+            grid.spawn_cell(&mut commands, &pos, PieceKind::I);
+        });
+
+    let mut game_over = app.world().resource::<Events<GameOver>>().get_reader();
+    crate::headless::run(&mut app, "wait 1\n".as_bytes());
+
+    let events = app.world().resource::<Events<GameOver>>();
+    let causes: Vec<_> = game_over.read(events).map(|event| event.cause).collect();
+    assert_eq!(causes, [GameEnd::TopOut(TopOut::BlockOut)]);
+
+    app.update();
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::GameOver);
+    assert_eq!(world.query::<&Fall>().iter(world).count(), 0);
+
+    // Restarting clears the grid and spawns a new piece
+    world.send_event(RestartGame);
+    app.update();
+    app.update();
+
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::Playing);
+    assert_eq!(world.query::<&FilledCell>().iter(world).count(), 0);
+    assert_eq!(world.query::<&Fall>().iter(world).count(), 1);
+}
+
+#[test]
+fn test_game_over_sent_once() {
+    // Run several ticks per update, while the game over state only applies
+    // after all of them
+    let game_over_causes = |mut app: App| {
+        let mut game_over = app.world().resource::<Events<GameOver>>().get_reader();
+        crate::headless::run(&mut app, "".as_bytes());
+
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep * 4));
+        app.update();
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<AppState>>(),
+            AppState::GameOver
+        );
+
+        let events = app.world().resource::<Events<GameOver>>();
+        game_over
+            .read(events)
+            .map(|event| event.cause)
+            .collect::<Vec<_>>()
+    };
+
+    // The spawn area stays blocked
+    let mut app = headless_app("scripted:T");
+    app.world_mut()
+        .run_system_once(|mut commands: Commands, mut grid: ResMut<GridState>| {
+            let pos = grid.config().spawn_position(PieceKind::T);
+            grid.spawn_cell(&mut commands, &pos, PieceKind::I);
+        });
+
+    assert_eq!(game_over_causes(app), [GameEnd::TopOut(TopOut::BlockOut)]);
+
+    // The time stays up
+    let settings = GameSettings {
+        mode: GameMode::Ultra(1),
+        ..Default::default()
+    };
+
+    let mut stopwatch = Stopwatch::new(&settings);
+    stopwatch.tick(Duration::from_secs(60));
+
+    let mut app = headless_app("scripted:T");
+    app.insert_resource(settings).insert_resource(stopwatch);
+
+    assert_eq!(game_over_causes(app), [GameEnd::TimeUp]);
+}
+
+#[test]
+fn test_headless_game_over_and_restart() {
+    let mut app = headless_app("scripted:O");
+
+    // Stacking O pieces at the center must eventually lock out
+    let exit = crate::headless::run(&mut app, "hard-drop\n".repeat(20).as_bytes());
+    assert_eq!(exit, AppExit::Success);
+
+    // State transition is applied on next update
+    app.update();
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::GameOver);
+    let cells = world.query::<&FilledCell>().iter(world).count();
+    assert_eq!(cells, 4 * 11);
+
+    world.send_event(RestartGame);
+    app.update();
+    app.update();
+
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::Playing);
+    let cells = world.query::<&FilledCell>().iter(world).count();
+    assert_eq!(cells, 0);
+}
+
+#[test]
+fn test_headless_pause() {
+    let mut app = headless_app("scripted:T");
+    crate::headless::run(&mut app, "wait 5\n".as_bytes());
+
+    let frozen = |app: &mut App| {
+        let world = app.world_mut();
+        let pos = *world.query::<(&GridPos, &Fall)>().single(world).0;
+        let stopwatch = world.resource::<Stopwatch>().since_begining;
+        (pos, stopwatch, world.resource::<GameTick>().0)
+    };
+
+    // Gravity, stopwatch and inputs are frozen while paused
+    let before = frozen(&mut app);
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Paused);
+    crate::headless::run(&mut app, "wait 120\nhard-drop\n".as_bytes());
+    assert_eq!(frozen(&mut app), before);
+
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Playing);
+    crate::headless::run(&mut app, "wait 120\n".as_bytes());
+    assert_ne!(frozen(&mut app), before);
+}
+
+#[test]
+fn test_headless_pause_during_soft_drop() {
+    let mut app = headless_app("scripted:T");
+    crate::headless::run(&mut app, "soft-drop\nwait 5\n".as_bytes());
+    assert!(app.world().resource::<SoftDrop>().active);
+
+    // The soft drop key may be released while the game is paused
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Paused);
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Playing);
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+    assert!(!app.world().resource::<SoftDrop>().active);
+
+    // The piece is back to normal gravity, without drop points
+    let falling = |app: &mut App| {
+        let world = app.world_mut();
+        let (pos, history) = world.query::<(&GridPos, &MoveHistory)>().single(world);
+        (pos.y, history.soft_drop_rows)
+    };
+
+    let (row, soft_drop_rows) = falling(&mut app);
+    crate::headless::run(&mut app, "wait 30\n".as_bytes());
+    assert!(row - falling(&mut app).0 <= 1);
+    assert_eq!(falling(&mut app).1, soft_drop_rows);
+}
+
+#[test]
+fn test_headless_perfect_clear() {
+    let mut app = headless_app("scripted:O");
+
+    // Five O pieces side by side clear two lines
+    let columns = [
+        "move-left\n".repeat(4),
+        "move-left\n".repeat(2),
+        String::new(),
+        "move-right\n".repeat(2),
+        "move-right\n".repeat(4),
+    ];
+
+    let input: String = columns.map(|moves| moves + "hard-drop\n").concat();
+    crate::headless::run(&mut app, input.as_bytes());
+
+    let world = app.world_mut();
+    // Each piece is hard dropped by 19 rows, after dropping one row at spawn
+    assert_eq!(world.resource::<Score>().0, 300 + 1200 + 5 * 2 * 19);
+    assert_eq!(world.resource::<ScoringState>().combo, 1);
+}
+
+#[test]
+fn test_headless_custom_board() {
+    let mut app = headless_app("scripted:O");
+    let board: BoardConfig = "4x8".parse().unwrap();
+    app.insert_resource(board)
+        .insert_resource(GridState::new(board));
+
+    // Two O pieces fill a 4-wide board
+    crate::headless::run(
+        &mut app,
+        "move-left\nhard-drop\nmove-right\nhard-drop\n".as_bytes(),
+    );
+
+    let world = app.world_mut();
+    // Each piece is hard dropped by 7 rows, after dropping one row at spawn
+    assert_eq!(world.resource::<Score>().0, 300 + 1200 + 2 * 2 * 7);
+    assert_eq!(world.resource::<XP>().lines, 2);
+
+    // The game still tops out above the visible rows
+    let input = "wait 60\n".to_string() + &"hard-drop\n".repeat(10);
+    let exit = crate::headless::run(&mut app, input.as_bytes());
+    assert_eq!(exit, AppExit::Success);
+    app.update();
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::GameOver);
+    let cells = world.query::<&FilledCell>().iter(world).count();
+    assert_eq!(cells, 4 * 5);
+}
+
+#[test]
+fn test_headless_drop_points() {
+    let mut app = headless_app("scripted:O");
+    crate::headless::run(
+        &mut app,
+        "soft-drop\nwait 10\nsoft-drop-release\n".as_bytes(),
+    );
+
+    let world = app.world_mut();
+    let pos = *world.query::<(&GridPos, &Fall)>().single(world).0;
+    let soft_drop_rows = u64::from(20 - pos.y);
+    assert!(soft_drop_rows > 0);
+
+    crate::headless::run(&mut app, "hard-drop\n".as_bytes());
+    let hard_drop_rows = 19 - soft_drop_rows;
+    let score = app.world().resource::<Score>().0;
+    assert_eq!(score, soft_drop_rows + 2 * hard_drop_rows);
+}
+
+/// Run device inputs and auto-repeat, with updates of 10ms.
+fn auto_shift_app(das: u64, arr: u64, opposite: OppositeDirection) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, InputPlugin, StatesPlugin))
+        .init_state::<AppState>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            10,
+        )))
+        .insert_resource(AutoShiftConfig {
+            das: Duration::from_millis(das),
+            arr: Duration::from_millis(arr),
+            dcd: Duration::ZERO,
+            opposite,
+        })
+        .init_resource::<AutoShiftState>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<KeyBindingsScreen>()
+        .init_resource::<GamepadBindings>()
+        .init_resource::<ButtonInput<Action>>()
+        .init_resource::<PlayerInputQueue>()
+        .add_systems(
+            Update,
+            (
+                collect_gamepad_presses,
+                collect_keyboard_presses,
+                auto_shift,
+            )
+                .chain(),
+        )
+        .add_systems(OnEnter(AppState::Paused), reset_auto_shift);
+
+    app.update();
+    app
+}
+
+/// Update the app `n` times and collect emitted inputs.
+fn auto_shift_run(app: &mut App, n: usize) -> Vec<PlayerInput> {
+    for _ in 0..n {
+        app.update();
+    }
+
+    app.world_mut()
+        .resource_mut::<PlayerInputQueue>()
+        .drain(..)
+        .collect()
+}
+
+fn auto_shift_key(app: &mut App, key_code: KeyCode, pressed: bool) {
+    app.world_mut().send_event(KeyboardInput {
+        key_code,
+        logical_key: Key::Unidentified(NativeKey::Unidentified),
+        state: if pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        },
+        window: Entity::PLACEHOLDER,
+    });
+}
+
+#[test]
+fn test_auto_shift_repeat() {
+    use PlayerInput::{MoveLeft, MoveRight};
+    let mut app = auto_shift_app(100, 20, OppositeDirection::LastPressed);
+
+    // Move once, then repeat when DAS is charged and after each ARR
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    assert_eq!(auto_shift_run(&mut app, 9), []);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    assert_eq!(auto_shift_run(&mut app, 4), [MoveLeft, MoveLeft]);
+
+    // Last pressed direction takes over, and the other resumes on release
+    auto_shift_key(&mut app, KeyCode::ArrowRight, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveRight]);
+    assert_eq!(auto_shift_run(&mut app, 9), []);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, false);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, false);
+    assert_eq!(auto_shift_run(&mut app, 20), []);
+
+    // A direction held before a pause doesn't move the piece once it resumes
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 5), [MoveLeft]);
+    let set_state = |app: &mut App, state| {
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(state);
+        auto_shift_run(app, 1)
+    };
+
+    set_state(&mut app, AppState::Paused);
+    assert_eq!(set_state(&mut app, AppState::Playing), []);
+    assert_eq!(auto_shift_run(&mut app, 20), []);
+    assert!(app.world().resource::<AutoShiftState>().held.is_empty());
+}
+
+#[test]
+fn test_auto_shift_cut_delay() {
+    use PlayerInput::{MoveLeft, MoveRight, RotateRight};
+    let mut app = auto_shift_app(100, 20, OppositeDirection::LastPressed);
+    app.world_mut().resource_mut::<AutoShiftConfig>().dcd = Duration::from_millis(50);
+
+    let press = |app: &mut App, key_code, n| {
+        auto_shift_key(app, key_code, true);
+        let inputs = auto_shift_run(app, n);
+        auto_shift_key(app, key_code, false);
+        inputs
+    };
+
+    // A rotation pauses the charge of the held direction
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    assert_eq!(auto_shift_run(&mut app, 5), []);
+    assert_eq!(press(&mut app, KeyCode::KeyX, 1), [RotateRight]);
+    assert_eq!(auto_shift_run(&mut app, 8), []);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, false);
+    assert_eq!(auto_shift_run(&mut app, 1), []);
+
+    // The delay elapses while no direction is held
+    assert_eq!(press(&mut app, KeyCode::KeyX, 1), [RotateRight]);
+    assert_eq!(auto_shift_run(&mut app, 10), []);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, true);
+    assert_eq!(auto_shift_run(&mut app, 10), [MoveRight]);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveRight]);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, false);
+    assert_eq!(auto_shift_run(&mut app, 1), []);
+
+    // It doesn't apply to a direction pressed after the rotation
+    assert_eq!(press(&mut app, KeyCode::KeyX, 1), [RotateRight]);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, true);
+    assert_eq!(auto_shift_run(&mut app, 10), [MoveRight]);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveRight]);
+}
+
+#[test]
+fn test_auto_shift_policies() {
+    use PlayerInput::{MoveLeft, ShiftLeft, ShiftRelease};
+
+    // Instant shift to the wall with ARR = 0
+    let mut app = auto_shift_app(50, 0, OppositeDirection::LastPressed);
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 6), [MoveLeft, ShiftLeft]);
+    assert_eq!(auto_shift_run(&mut app, 10), []);
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, false);
+    assert_eq!(auto_shift_run(&mut app, 1), [ShiftRelease]);
+
+    // First direction is kept
+    let mut app = auto_shift_app(50, 10, OppositeDirection::FirstPressed);
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, true);
+    assert_eq!(auto_shift_run(&mut app, 5), [MoveLeft]);
+
+    // Both directions cancel each other
+    let mut app = auto_shift_app(50, 10, OppositeDirection::Cancel);
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    auto_shift_key(&mut app, KeyCode::ArrowRight, true);
+    assert_eq!(auto_shift_run(&mut app, 10), []);
+}
+
+#[test]
+fn test_key_bindings() {
+    let mut bindings = KeyBindings::default();
+    assert_eq!(
+        KeyBindings::from_ron(&bindings.to_ron()),
+        Ok(bindings.clone())
+    );
+
+    // A key triggers a single action once rebound
+    bindings.bind(Action::HardDrop, KeyCode::ArrowUp);
+    assert_eq!(bindings.keys[Action::HardDrop], [KeyCode::ArrowUp]);
+    assert_eq!(bindings.keys[Action::RotateRight], [KeyCode::KeyX]);
+    assert_eq!(bindings.describe(Action::RotateRight), "X");
+
+    // Missing actions keep their default keys
+    let bindings = KeyBindings::from_ron("{ hold: [KeyH], quit: [] }").unwrap();
+    assert_eq!(bindings.keys[Action::Hold], [KeyCode::KeyH]);
+    assert_eq!(bindings.describe(Action::Quit), "-");
+    assert_eq!(
+        bindings.keys[Action::Pause],
+        [KeyCode::Escape, KeyCode::KeyP]
+    );
+    assert!(KeyBindings::from_ron("{ jump: [Space] }").is_err());
+
+    // Auto-repeat follows rebound keys
+    let mut app = auto_shift_app(100, 20, OppositeDirection::LastPressed);
+    let mut bindings = KeyBindings::default();
+    bindings.bind(Action::MoveLeft, KeyCode::KeyJ);
+    app.insert_resource(bindings);
+
+    auto_shift_key(&mut app, KeyCode::ArrowLeft, true);
+    assert_eq!(auto_shift_run(&mut app, 1), []);
+    auto_shift_key(&mut app, KeyCode::KeyJ, true);
+    assert_eq!(auto_shift_run(&mut app, 1), [PlayerInput::MoveLeft]);
+}
+
+#[test]
+fn test_gamepad() {
+    use GamepadButtonType::{DPadLeft, East, South};
+    use PlayerInput::{MoveLeft, MoveRight, RotateLeft, RotateRight, SoftDrop, SoftDropRelease};
+
+    let mut app = auto_shift_app(100, 20, OppositeDirection::LastPressed);
+    let pad = Gamepad::new(0);
+
+    let mut mapping = GamepadMapping::default();
+    mapping.buttons.insert(Action::RotateRight, vec![South]);
+    mapping.buttons.insert(Action::RotateLeft, vec![East]);
+    let mut bindings = GamepadBindings::default();
+    bindings.gamepads.insert("Custom Pad".to_string(), mapping);
+    app.insert_resource(bindings);
+
+    let send = |app: &mut App, event: GamepadEvent| app.world_mut().send_event(event);
+    let connect = |name: &str| {
+        let info = GamepadInfo {
+            name: name.to_string(),
+        };
+        GamepadConnectionEvent::new(pad, GamepadConnection::Connected(info)).into()
+    };
+
+    send(&mut app, connect("Generic Pad"));
+    assert_eq!(auto_shift_run(&mut app, 1), []);
+
+    // D-pad repeats with the same timings as the keyboard
+    send(
+        &mut app,
+        GamepadButtonChangedEvent::new(pad, DPadLeft, 1.0).into(),
+    );
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveLeft]);
+    assert_eq!(auto_shift_run(&mut app, 10), [MoveLeft]);
+    send(
+        &mut app,
+        GamepadButtonChangedEvent::new(pad, DPadLeft, 0.0).into(),
+    );
+    assert_eq!(auto_shift_run(&mut app, 10), []);
+
+    send(
+        &mut app,
+        GamepadButtonChangedEvent::new(pad, South, 1.0).into(),
+    );
+    assert_eq!(auto_shift_run(&mut app, 1), [RotateLeft]);
+    send(
+        &mut app,
+        GamepadButtonChangedEvent::new(pad, South, 0.0).into(),
+    );
+
+    // Left stick moves and soft drops
+    let stick = |axis_type, value| GamepadAxisChangedEvent::new(pad, axis_type, value).into();
+    send(&mut app, stick(GamepadAxisType::LeftStickX, 0.8));
+    assert_eq!(auto_shift_run(&mut app, 1), [MoveRight]);
+    send(&mut app, stick(GamepadAxisType::LeftStickX, 0.0));
+    send(&mut app, stick(GamepadAxisType::LeftStickY, -0.9));
+    assert_eq!(auto_shift_run(&mut app, 1), [SoftDrop]);
+
+    // Held inputs are released when the gamepad is unplugged
+    send(
+        &mut app,
+        GamepadConnectionEvent::new(pad, GamepadConnection::Disconnected).into(),
+    );
+    assert_eq!(auto_shift_run(&mut app, 1), [SoftDropRelease]);
+
+    // Mappings apply by name of the gamepad
+    send(&mut app, connect("Custom Pad"));
+    send(
+        &mut app,
+        GamepadButtonChangedEvent::new(pad, South, 1.0).into(),
+    );
+    assert_eq!(auto_shift_run(&mut app, 1), [RotateRight]);
+}
+
+#[test]
+fn test_game_settings_apply_on_restart() {
+    let mut app = headless_app("7-bag");
+    app.insert_resource(GameSettings {
+        mode: GameMode::default(),
+        start_level: 5,
+        ..Default::default()
+    });
+
+    app.world_mut().send_event(RestartGame);
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+    assert_eq!(app.world().resource::<XP>().level(), 5);
+
+    let mut best_scores = BestScores::default();
+    assert!(best_scores.register(GameMode::default(), 1200));
+    assert!(!best_scores.register(GameMode::default(), 800));
+    assert_eq!(best_scores.0[&GameMode::default()], 1200);
+}
+
+#[test]
+fn test_restart_seed() {
+    let mut app = headless_app("random");
+
+    let pieces = |app: &mut App| {
+        crate::headless::run(app, "wait\n".as_bytes());
+        let mut generator = app.world_mut().resource_mut::<PieceGenerator>();
+        let pieces: Vec<_> = generator.peek_n(20).collect();
+        (generator.seed, pieces)
+    };
+
+    // A given seed is kept for every game
+    let first = pieces(&mut app);
+    assert_eq!(first.0, 0);
+    app.world_mut().send_event(RestartGame);
+    assert_eq!(pieces(&mut app), first);
+
+    // Otherwise each game draws its own seed
+    app.world_mut().resource_mut::<RandomizerConfig>().seed = None;
+    app.world_mut().send_event(RestartGame);
+    let second = pieces(&mut app);
+    app.world_mut().send_event(RestartGame);
+    assert_ne!(pieces(&mut app).0, second.0);
+}
+
+#[test]
+fn test_sprint_goal() {
+    assert_eq!("sprint-40".parse(), Ok(GameMode::Sprint(40)));
+    assert!("sprint-0".parse::<GameMode>().is_err());
+
+    let mut app = headless_app("scripted:O");
+    app.insert_resource(GameSettings {
+        mode: GameMode::Sprint(2),
+        start_level: 5,
+        ..Default::default()
+    });
+
+    app.world_mut().send_event(RestartGame);
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+    assert_eq!(app.world().resource::<XP>().level(), 1);
+    assert_eq!(app.world().resource::<XP>().lines_remaining(), Some(2));
+
+    let columns = [
+        "move-left\n".repeat(4),
+        "move-left\n".repeat(2),
+        String::new(),
+        "move-right\n".repeat(2),
+        "move-right\n".repeat(4),
+    ];
+
+    let input: String = columns.map(|moves| moves + "hard-drop\n").concat();
+    let exit = crate::headless::run(&mut app, input.as_bytes());
+    assert_eq!(exit, AppExit::Success);
+
+    app.update();
+    let world = app.world();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::GameOver);
+    assert_eq!(world.resource::<XP>().lines_remaining(), Some(0));
+    assert_eq!(world.resource::<GameStats>().pieces, 5);
+    assert_eq!(world.resource::<GameStats>().inputs, 12 + 5);
+
+    let stopwatch = world.resource::<Stopwatch>();
+    assert!(stopwatch.precise);
+    assert!(stopwatch.to_string().contains('.'));
+
+    // Sprints are ranked by time
+    let mut best_scores = BestScores::default();
+    assert!(best_scores.register(GameMode::Sprint(40), 60_000));
+    assert!(best_scores.register(GameMode::Sprint(40), 55_000));
+    assert!(!best_scores.register(GameMode::Sprint(40), 58_000));
+}
+
+#[test]
+fn test_ultra_time_up() {
+    assert_eq!("ultra-3".parse(), Ok(GameMode::Ultra(3)));
+
+    let mut app = headless_app("scripted:O");
+    app.insert_resource(GameSettings {
+        mode: GameMode::Ultra(1),
+        start_level: 1,
+        ..Default::default()
+    });
+
+    app.world_mut().send_event(RestartGame);
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+
+    // The stopwatch counts down
+    let display = app.world().resource::<Stopwatch>().to_string();
+    assert!(display.starts_with("00:59."), "{display}");
+
+    let columns = [
+        "move-left\n".repeat(4),
+        "move-left\n".repeat(2),
+        String::new(),
+        "move-right\n".repeat(2),
+        "move-right\n".repeat(4),
+    ];
+
+    let input: String = columns.map(|moves| moves + "hard-drop\n").concat();
+    crate::headless::run(&mut app, input.as_bytes());
+    assert_eq!(
+        app.world().resource::<State<AppState>>(),
+        &AppState::Playing
+    );
+
+    let exit = crate::headless::run(&mut app, "wait 3700\n".as_bytes());
+    assert_eq!(exit, AppExit::Success);
+
+    app.update();
+    let world = app.world();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::GameOver);
+    assert!(world.resource::<Stopwatch>().time_up());
+    assert_eq!(world.resource::<Stopwatch>().to_string(), "00:00.000");
+
+    // Perfect clear bonus is counted with the clear
+    let points = &world.resource::<GameStats>().points;
+    assert_eq!(points[&ScoreSource::Lines(2)], 300 + 1200);
+    assert_eq!(points[&ScoreSource::Drops], 5 * 2 * 19);
+    assert_eq!(ScoreSource::TSpinMini(0).label(), "T-Spin Mini");
+    assert_eq!(ScoreSource::Lines(4).label(), "Tetris");
+}
+
+#[test]
+fn test_marathon_goal() {
+    let tetris = LineClear {
+        lines: 4,
+        t_spin: TSpin::None,
+        perfect_clear: false,
+    };
+
+    let settings = |mode: &str| GameSettings {
+        mode: mode.parse().unwrap(),
+        start_level: 14,
+        ..Default::default()
+    };
+
+    // Fixed goal finishes after 20 lines from level 14
+    let mut xp = XP::new(&settings("marathon"));
+    (0..5).for_each(|_| xp.register(&tetris, false));
+    assert_eq!((xp.level(), xp.goal_reached()), (15, true));
+
+    // Variable goal awards 8 lines per tetris, levels 14 and 15 need 145
+    let mut xp = XP::new(&settings("marathon-variable"));
+    (0..18).for_each(|_| xp.register(&tetris, false));
+    assert!(!xp.goal_reached());
+    xp.register(&tetris, false);
+    assert!(xp.goal_reached());
+
+    let mut xp = XP::new(&settings("marathon-endless"));
+    (0..50).for_each(|_| xp.register(&tetris, false));
+    assert_eq!((xp.level(), xp.goal_reached()), (34, false));
+
+    // Modes are saved under their names
+    let scores = BestScores([(GameMode::Sprint(40), 1)].into());
+    assert_eq!(ron::to_string(&scores.0).unwrap(), "{\"sprint-40\":1}");
+}
+
+#[test]
+fn test_lock_delay_policies() {
+    let landed_app = |lock_delay| {
+        let mut app = headless_app("scripted:O");
+        app.insert_resource(GameSettings {
+            start_level: 15,
+            lock_delay,
+            ..Default::default()
+        });
+
+        // Gravity is fast enough for the piece to land quickly
+        app.world_mut().send_event(RestartGame);
+        crate::headless::run(&mut app, "wait 15\n".as_bytes());
+
+        let world = app.world_mut();
+        let (&kind, &pos, &spin) = world.query::<(&PieceKind, &GridPos, &Spin)>().single(world);
+        assert!(world.resource::<GridState>().is_grounded(kind, pos, spin));
+        app
+    };
+
+    // Each move comes before the lock delay of 30 ticks expires
+    let moves = |count| -> String {
+        (0..count)
+            .map(|i| match i % 2 {
+                0 => "move-left\nwait 20\n",
+                _ => "move-right\nwait 20\n",
+            })
+            .collect()
+    };
+
+    let locked = |app: &mut App| {
+        let world = app.world_mut();
+        world.query::<&FilledCell>().iter(world).count() > 0
+    };
+
+    // Extended placement forces the lock after 15 resets
+    let mut app = landed_app(LockDelay::Extended);
+    crate::headless::run(&mut app, moves(14).as_bytes());
+    assert!(!locked(&mut app));
+    let fall = app.world_mut().query::<&Fall>().single(app.world()).clone();
+    assert_eq!(fall.lock_resets, 14);
+    crate::headless::run(&mut app, moves(1).as_bytes());
+    assert!(locked(&mut app));
+
+    // Infinite placement never locks while the piece keeps moving
+    let mut app = landed_app(LockDelay::Infinite);
+    crate::headless::run(&mut app, moves(30).as_bytes());
+    assert!(!locked(&mut app));
+    crate::headless::run(&mut app, "wait 30\n".as_bytes());
+    assert!(locked(&mut app));
+
+    // Step reset ignores moves on the same row
+    let mut app = landed_app(LockDelay::Step);
+    crate::headless::run(&mut app, moves(2).as_bytes());
+    assert!(locked(&mut app));
+}
+
+#[test]
+fn test_lock_delay_new_row() {
+    let mut app = headless_app("scripted:T");
+    crate::headless::run(&mut app, "wait\n".as_bytes());
+
+    let falling = |app: &mut App| {
+        let world = app.world_mut();
+        let (pos, fall) = world.query::<(&GridPos, &Fall)>().single(world);
+        (pos.y, fall.lowest_row, fall.lock_resets)
+    };
+
+    let world = app.world_mut();
+    world.query::<&mut Fall>().single_mut(world).lock_resets = MAX_LOCK_RESETS;
+    let (row, _, _) = falling(&mut app);
+
+    // Resets are restored during the tick where gravity reaches a new row
+    for _ in 0..60 {
+        app.update();
+
+        if falling(&mut app).0 != row {
+            break;
+        }
+    }
+
+    assert_eq!(falling(&mut app), (row - 1, row - 1, 0));
+}
+
+#[test]
+fn test_initial_rotation_and_hold() {
+    let mut app = headless_app("scripted:TIO");
+
+    let falling = |app: &mut App| {
+        let world = app.world_mut();
+        let (&kind, &spin) = world.query::<(&PieceKind, &Spin)>().single(world);
+        (kind, spin)
+    };
+
+    // Rotation entered before the piece appears is applied at spawn
+    crate::headless::run(&mut app, "hard-drop\nrotate-right\n".as_bytes());
+    assert_eq!(falling(&mut app), (PieceKind::I, Spin(1)));
+
+    // Hold swaps the next piece before it appears
+    crate::headless::run(&mut app, "hard-drop\nhold\n".as_bytes());
+    assert_eq!(falling(&mut app), (PieceKind::T, Spin(0)));
+    assert_eq!(app.world().resource::<HeldPiece>().kind, Some(PieceKind::O));
+}
+
+#[test]
+fn test_hold() {
+    let mut app = headless_app("scripted:TIO");
+
+    let falling = |app: &mut App| {
+        let world = app.world_mut();
+        let (&kind, &pos, &spin, fall) = world
+            .query::<(&PieceKind, &GridPos, &Spin, &Fall)>()
+            .single(world);
+        (kind, pos, spin, fall.down_timer.elapsed(), fall.lowest_row)
+    };
+
+    let held = |app: &App| {
+        let held = app.world().resource::<HeldPiece>();
+        (held.kind, held.locked)
+    };
+
+    let spawn = |app: &App, kind| {
+        app.world()
+            .resource::<GridState>()
+            .spawn(kind, Spin(0))
+            .unwrap()
+    };
+
+    // Move, rotate and let the piece fall a row before holding it
+    crate::headless::run(
+        &mut app,
+        "wait 70\nmove-left\nrotate-right\nwait 10\n".as_bytes(),
+    );
+    let (_, pos, spin, _, lowest_row) = falling(&mut app);
+    assert_eq!(spin, Spin(1));
+    assert!(pos.y < spawn(&app, PieceKind::T).y);
+    assert_eq!(lowest_row, pos.y);
+
+    // The first hold pulls the next piece from the queue, which starts over
+    // from the spawn position
+    crate::headless::run(&mut app, "hold\n".as_bytes());
+    let pos = spawn(&app, PieceKind::I);
+    let (kind, piece_pos, spin, elapsed, lowest_row) = falling(&mut app);
+    assert_eq!((kind, piece_pos, spin), (PieceKind::I, pos, Spin(0)));
+    assert_eq!(lowest_row, pos.y);
+    assert!(elapsed < Duration::from_millis(20));
+    assert_eq!(held(&app), (Some(PieceKind::T), true));
+
+    // Only one swap is allowed until the next piece spawns
+    crate::headless::run(&mut app, "hold\nmove-right\n".as_bytes());
+    assert_eq!(falling(&mut app).0, PieceKind::I);
+    assert_eq!(held(&app), (Some(PieceKind::T), true));
+
+    // The queue moved on, and swapping is allowed again for the next piece
+    crate::headless::run(&mut app, "hard-drop\nwait\n".as_bytes());
+    assert_eq!(falling(&mut app).0, PieceKind::O);
+    assert_eq!(held(&app), (Some(PieceKind::T), false));
+
+    crate::headless::run(&mut app, "hold\n".as_bytes());
+    assert_eq!(falling(&mut app).0, PieceKind::T);
+    assert_eq!(held(&app), (Some(PieceKind::O), true));
+}
+
+#[test]
+fn test_bot_spin() {
+    let mut app = headless_app("scripted:T");
+    app.add_plugins(BotPlugin {
+        enabled: true,
+        weights: Weights::default(),
+        delay: Duration::ZERO,
+    });
+
+    // T-slot which is covered on its left
+    app.world_mut()
+        .run_system_once(|mut commands: Commands, mut grid: ResMut<GridState>| {
+            let rows = ["...#......", "###...####", "####.#####"];
+
+            for (y, row) in rows.iter().rev().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c == '#' {
+                        let pos = GridPos {
+                            x: x as u8,
+                            y: y as u8,
+                        };
+
+                        grid.spawn_cell(&mut commands, &pos, PieceKind::I);
+                    }
+                }
+            }
+        });
+
+    // The bot holds the soft drop down to the slot, then spins into it
+    crate::headless::run(&mut app, "wait 100\n".as_bytes());
+    let points = &app.world().resource::<GameStats>().points;
+    assert!(points.contains_key(&ScoreSource::TSpin(2)));
+}
+
+#[test]
+fn test_bot_player() {
+    let mut app = headless_app("7-bag");
+    app.add_plugins(BotPlugin {
+        enabled: true,
+        weights: Weights::default(),
+        delay: Duration::ZERO,
+    });
+
+    // The bot places a piece on every tick, apart from clear delays
+    crate::headless::run(&mut app, "wait 300\n".as_bytes());
+    let world = app.world_mut();
+    assert_eq!(*world.resource::<State<AppState>>(), AppState::Playing);
+    assert!(world.resource::<XP>().lines >= 10);
+
+    // Nothing happens once the bot is switched off
+    world.resource_mut::<BotPlayer>().enabled = false;
+    crate::headless::run(&mut app, "wait 1\n".as_bytes());
+    let pieces = app.world().resource::<GameStats>().pieces;
+    crate::headless::run(&mut app, "wait 60\n".as_bytes());
+    assert_eq!(app.world().resource::<GameStats>().pieces, pieces);
+
+    // With a delay, a single input is sent every time it elapsed
+    let mut app = headless_app("scripted:T");
+    app.add_plugins(BotPlugin {
+        enabled: true,
+        weights: Weights::default(),
+        delay: Duration::from_millis(100),
+    });
+
+    crate::headless::run(&mut app, "wait 60\n".as_bytes());
+    let stats = app.world().resource::<GameStats>();
+    assert!((9..=10).contains(&stats.inputs), "{}", stats.inputs);
+    assert!(stats.pieces < 5);
+}
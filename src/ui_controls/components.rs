@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Screen displayed while rebinding keys.
+#[derive(Component)]
+pub(crate) struct KeyBindingsOverlay;
@@ -1,3 +1,4 @@
+pub(crate) mod components;
 pub(crate) mod plugin;
 pub(crate) mod resources;
 pub(crate) mod systems;
@@ -1,23 +1,47 @@
 use bevy::prelude::*;
 
+use crate::game_rules::resources::AppState;
+
 use super::resources::*;
 use super::systems::*;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct UiControlsSystems;
 
-pub(crate) struct UiControlsPlugin;
+pub(crate) struct UiControlsPlugin {
+    pub(crate) auto_shift: AutoShiftConfig,
+}
 
 impl Plugin for UiControlsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PlayerInputQueue>()
-            .init_resource::<TouchStateRegistry>()
+        app.init_resource::<TouchStateRegistry>()
+            .init_resource::<AutoShiftState>()
+            .init_resource::<KeyBindingsScreen>()
+            .init_resource::<PauseMenu>()
+            .insert_resource(self.auto_shift.clone())
+            .init_resource::<ButtonInput<Action>>()
+            .insert_resource(KeyBindings::load())
+            .insert_resource(GamepadBindings::load())
+            .add_systems(
+                OnEnter(AppState::Paused),
+                (reset_pause_menu, reset_auto_shift),
+            )
             .add_systems(
                 Update,
                 (
                     bevy::input::keyboard::keyboard_input_system,
                     bevy::input::touch::touch_screen_input_system,
+                    key_bindings_screen_input,
+                    collect_gamepad_presses,
+                    meta_actions,
+                    pause_on_focus_loss,
+                    pause_menu_input.run_if(in_state(AppState::Paused)),
                     collect_keyboard_presses,
+                    auto_shift,
+                    draw_key_bindings_screen.run_if(
+                        resource_changed::<KeyBindingsScreen>
+                            .or_else(resource_changed::<KeyBindings>),
+                    ),
                     debug_touchscreen,
                     touch_start,
                     collect_touch_moves,
@@ -1,47 +1,487 @@
-use std::collections::{HashMap, VecDeque};
-use std::ops::{Deref, DerefMut};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
 
 use bevy::prelude::*;
+use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
 
-// -- PlayerInputQueue
+use crate::common::storage;
 
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum PlayerInput {
+use crate::game_rules::resources::PlayerInput;
+
+// -- TouchState
+
+pub(crate) struct TouchState {
+    pub(crate) start_position: Vec2,
+    pub(crate) drawn_input: Option<PlayerInput>,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct TouchStateRegistry {
+    pub(crate) touch_start: HashMap<u64, TouchState>,
+}
+
+// -- AutoShift
+
+/// Behavior when both horizontal directions are held.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum OppositeDirection {
+    /// The last pressed direction takes over
+    #[default]
+    LastPressed,
+    /// The first pressed direction is kept until it is released
+    FirstPressed,
+    /// The piece doesn't move while both directions are held
+    Cancel,
+}
+
+impl FromStr for OppositeDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last" => Ok(Self::LastPressed),
+            "first" => Ok(Self::FirstPressed),
+            "cancel" => Ok(Self::Cancel),
+            _ => Err(format!("unknown direction priority `{s}`")),
+        }
+    }
+}
+
+/// Settings of horizontal auto-repeat.
+/// See https://tetris.wiki/DAS
+#[derive(Resource, Clone, Debug)]
+pub(crate) struct AutoShiftConfig {
+    /// Delayed auto shift: time a direction is held before it repeats
+    pub(crate) das: Duration,
+    /// Auto repeat rate: time between repeated moves, zero moves to the wall
+    pub(crate) arr: Duration,
+    /// DAS cut delay: auto-repeat pauses for this long after a rotation,
+    /// hold or hard drop
+    pub(crate) dcd: Duration,
+    pub(crate) opposite: OppositeDirection,
+}
+
+impl Default for AutoShiftConfig {
+    fn default() -> Self {
+        let (das, arr) = HandlingProfile::Standard.timings();
+
+        Self {
+            das,
+            arr,
+            dcd: Duration::ZERO,
+            opposite: OppositeDirection::default(),
+        }
+    }
+}
+
+/// Presets of auto-repeat timings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HandlingProfile {
+    Standard,
+    Fast,
+    /// Pieces move to the wall once DAS is charged
+    Instant,
+}
+
+impl HandlingProfile {
+    pub(crate) const fn all() -> [Self; 3] {
+        [Self::Standard, Self::Fast, Self::Instant]
+    }
+
+    pub(crate) const fn label(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::Fast => "Fast",
+            Self::Instant => "Instant",
+        }
+    }
+
+    /// DAS and ARR of the profile.
+    pub(crate) const fn timings(self) -> (Duration, Duration) {
+        match self {
+            Self::Standard => (Duration::from_millis(167), Duration::from_millis(33)),
+            Self::Fast => (Duration::from_millis(117), Duration::from_millis(17)),
+            Self::Instant => (Duration::from_millis(100), Duration::ZERO),
+        }
+    }
+}
+
+impl AutoShiftConfig {
+    /// Profile matching current timings, if they were not customized.
+    pub(crate) fn profile(&self) -> Option<HandlingProfile> {
+        HandlingProfile::all()
+            .into_iter()
+            .find(|profile| profile.timings() == (self.das, self.arr))
+    }
+
+    pub(crate) fn set_profile(&mut self, profile: HandlingProfile) {
+        (self.das, self.arr) = profile.timings();
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct AutoShiftState {
+    /// Held directions, in order of press
+    pub(crate) held: Vec<PlayerInput>,
+    /// Direction currently moving the piece
+    pub(crate) active: Option<PlayerInput>,
+    /// Time the active direction has been held, paused by DCD
+    pub(crate) charge: Duration,
+    /// Number of repeated moves sent since the active direction was pressed
+    pub(crate) repeats: u32,
+    /// Remaining pause of auto-repeat
+    pub(crate) cut: Duration,
+    /// An instant shift to the wall has been sent and not released yet
+    pub(crate) shifting: bool,
+}
+
+// -- KeyBindings
+
+/// Name of the file key bindings are saved to, in the config directory of
+/// the user (or the local storage on web).
+const KEY_BINDINGS_FILE: &str = "key-bindings.ron";
+
+/// Action that can be bound to keys.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, enum_map::Enum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Action {
     MoveLeft,
     MoveRight,
+    SoftDrop,
     HardDrop,
     RotateRight,
     RotateLeft,
-    // SoftDrop,
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+    Quit,
+    KeyBindings,
+    ToggleBot,
 }
 
-#[derive(Resource, Default)]
-pub(crate) struct PlayerInputQueue {
-    pub(crate) queue: VecDeque<PlayerInput>,
+impl Action {
+    pub(crate) const fn all() -> [Self; 13] {
+        [
+            Self::MoveLeft,
+            Self::MoveRight,
+            Self::SoftDrop,
+            Self::HardDrop,
+            Self::RotateRight,
+            Self::RotateLeft,
+            Self::Rotate180,
+            Self::Hold,
+            Self::Pause,
+            Self::Restart,
+            Self::Quit,
+            Self::KeyBindings,
+            Self::ToggleBot,
+        ]
+    }
+
+    /// Input sent to the game when a key bound to this action is pressed.
+    pub(crate) const fn player_input(self) -> Option<PlayerInput> {
+        match self {
+            Self::MoveLeft => Some(PlayerInput::MoveLeft),
+            Self::MoveRight => Some(PlayerInput::MoveRight),
+            Self::SoftDrop => Some(PlayerInput::SoftDrop),
+            Self::HardDrop => Some(PlayerInput::HardDrop),
+            Self::RotateRight => Some(PlayerInput::RotateRight),
+            Self::RotateLeft => Some(PlayerInput::RotateLeft),
+            Self::Rotate180 => Some(PlayerInput::Rotate180),
+            Self::Hold => Some(PlayerInput::Hold),
+            Self::Pause | Self::Restart | Self::Quit | Self::KeyBindings | Self::ToggleBot => None,
+        }
+    }
+
+    pub(crate) const fn label(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::SoftDrop => "Soft drop",
+            Self::HardDrop => "Hard drop",
+            Self::RotateRight => "Rotate right",
+            Self::RotateLeft => "Rotate left",
+            Self::Rotate180 => "Rotate 180",
+            Self::Hold => "Hold",
+            Self::Pause => "Pause",
+            Self::Restart => "Restart",
+            Self::Quit => "Quit",
+            Self::KeyBindings => "Key bindings",
+            Self::ToggleBot => "Toggle bot",
+        }
+    }
 }
 
-impl Deref for PlayerInputQueue {
-    type Target = VecDeque<PlayerInput>;
+/// Short name of a key, as displayed to the player.
+pub(crate) fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
 
-    fn deref(&self) -> &Self::Target {
-        &self.queue
+    ["Key", "Digit"]
+        .into_iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .map(str::to_string)
+        .unwrap_or(name)
+}
+
+/// Physical keys bound to each action, several keys can trigger the same
+/// action.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeyBindings {
+    pub(crate) keys: EnumMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: EnumMap::from_fn(|action| match action {
+                Action::MoveLeft => vec![KeyCode::ArrowLeft],
+                Action::MoveRight => vec![KeyCode::ArrowRight],
+                Action::SoftDrop => vec![KeyCode::ArrowDown],
+                Action::HardDrop => vec![KeyCode::Space],
+                Action::RotateRight => vec![KeyCode::ArrowUp, KeyCode::KeyX],
+                Action::RotateLeft => {
+                    vec![KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::KeyZ]
+                }
+                Action::Rotate180 => vec![KeyCode::KeyA],
+                Action::Hold => vec![KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::KeyC],
+                Action::Pause => vec![KeyCode::Escape, KeyCode::KeyP],
+                Action::Restart => vec![KeyCode::KeyR],
+                Action::Quit => vec![KeyCode::KeyQ],
+                Action::KeyBindings => vec![KeyCode::F1],
+                Action::ToggleBot => vec![KeyCode::KeyB],
+            }),
+        }
     }
 }
 
-impl DerefMut for PlayerInputQueue {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.queue
+impl KeyBindings {
+    /// Actions triggered by a key.
+    pub(crate) fn actions(&self, key: KeyCode) -> impl Iterator<Item = Action> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(_, keys)| keys.contains(&key))
+            .map(|(action, _)| action)
+    }
+
+    pub(crate) fn pressed(&self, keyboard: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keyboard.any_pressed(self.keys[action].iter().copied())
+    }
+
+    pub(crate) fn just_pressed(&self, keyboard: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keyboard.any_just_pressed(self.keys[action].iter().copied())
+    }
+
+    /// Keys bound to an action, as displayed to the player.
+    pub(crate) fn describe(&self, action: Action) -> String {
+        let names: Vec<_> = self.keys[action].iter().copied().map(key_name).collect();
+
+        if names.is_empty() {
+            "-".to_string()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    /// Bind a key to an action alone, replacing its previous keys.
+    pub(crate) fn bind(&mut self, action: Action, key: KeyCode) {
+        for keys in self.keys.values_mut() {
+            keys.retain(|&bound| bound != key);
+        }
+
+        self.keys[action] = vec![key];
+    }
+
+    /// Parse bindings from RON, actions that are missing keep their default
+    /// keys.
+    pub(crate) fn from_ron(data: &str) -> Result<Self, ron::error::SpannedError> {
+        let mut res = Self::default();
+        let keys: BTreeMap<Action, Vec<KeyCode>> = ron::from_str(data)?;
+
+        for (action, keys) in keys {
+            res.keys[action] = keys;
+        }
+
+        Ok(res)
+    }
+
+    pub(crate) fn to_ron(&self) -> String {
+        let keys: BTreeMap<_, _> = self.keys.iter().collect();
+        ron::ser::to_string_pretty(&keys, ron::ser::PrettyConfig::default())
+            .expect("key bindings should serialize")
+    }
+
+    /// Load bindings saved by the player, or the default ones.
+    pub(crate) fn load() -> Self {
+        let Some(data) = storage::read(KEY_BINDINGS_FILE) else {
+            return Self::default();
+        };
+
+        Self::from_ron(&data).unwrap_or_else(|err| {
+            warn!("Invalid key bindings, using defaults: {err}");
+            Self::default()
+        })
+    }
+
+    pub(crate) fn save(&self) {
+        if let Err(err) = storage::write(KEY_BINDINGS_FILE, &self.to_ron()) {
+            warn!("Could not save key bindings: {err}");
+        }
     }
 }
 
-// -- TouchState
+// -- GamepadBindings
 
-pub(crate) struct TouchState {
-    pub(crate) start_position: Vec2,
-    pub(crate) drawn_input: Option<PlayerInput>,
+/// Name of the file gamepad bindings are saved to.
+const GAMEPAD_BINDINGS_FILE: &str = "gamepad-bindings.ron";
+
+/// Buttons bound to each action for a gamepad. The left stick always moves
+/// the piece and soft drops, like the D-pad.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GamepadMapping {
+    pub(crate) buttons: BTreeMap<Action, Vec<GamepadButtonType>>,
+    /// How far the stick must be tilted to trigger a direction, from 0 to 1
+    pub(crate) stick_threshold: f32,
 }
 
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+
+        let buttons = [
+            (Action::MoveLeft, vec![DPadLeft]),
+            (Action::MoveRight, vec![DPadRight]),
+            (Action::SoftDrop, vec![DPadDown]),
+            (Action::HardDrop, vec![DPadUp]),
+            (Action::RotateRight, vec![East]),
+            (Action::RotateLeft, vec![South]),
+            (Action::Rotate180, vec![North]),
+            (Action::Hold, vec![West, LeftTrigger, RightTrigger]),
+            (Action::Pause, vec![Start]),
+            (Action::Restart, vec![Select]),
+        ];
+
+        Self {
+            buttons: buttons.into_iter().collect(),
+            stick_threshold: 0.5,
+        }
+    }
+}
+
+impl GamepadMapping {
+    /// Actions held on a gamepad.
+    pub(crate) fn pressed(
+        &self,
+        gamepad: Gamepad,
+        buttons: &ButtonInput<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> impl Iterator<Item = Action> + '_ {
+        let stick = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+
+        let stick_x = stick(GamepadAxisType::LeftStickX);
+        let stick_y = stick(GamepadAxisType::LeftStickY);
+
+        let stick_actions = [
+            (Action::MoveLeft, stick_x <= -self.stick_threshold),
+            (Action::MoveRight, stick_x >= self.stick_threshold),
+            (Action::SoftDrop, stick_y <= -self.stick_threshold),
+        ];
+
+        let button_actions: Vec<_> = self
+            .buttons
+            .iter()
+            .filter(|(_, types)| {
+                types
+                    .iter()
+                    .any(|&button_type| buttons.pressed(GamepadButton::new(gamepad, button_type)))
+            })
+            .map(|(&action, _)| action)
+            .collect();
+
+        stick_actions
+            .into_iter()
+            .filter(|(_, pressed)| *pressed)
+            .map(|(action, _)| action)
+            .chain(button_actions)
+    }
+}
+
+/// Gamepad mappings, which can be overridden for each model of gamepad.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GamepadBindings {
+    pub(crate) default: GamepadMapping,
+    /// Mappings by name of the gamepad, as reported by the driver
+    pub(crate) gamepads: BTreeMap<String, GamepadMapping>,
+}
+
+impl GamepadBindings {
+    pub(crate) fn mapping(&self, name: Option<&str>) -> &GamepadMapping {
+        name.and_then(|name| self.gamepads.get(name))
+            .unwrap_or(&self.default)
+    }
+
+    /// Load bindings saved by the player, or the default ones.
+    pub(crate) fn load() -> Self {
+        let Some(data) = storage::read(GAMEPAD_BINDINGS_FILE) else {
+            return Self::default();
+        };
+
+        ron::from_str(&data).unwrap_or_else(|err| {
+            warn!("Invalid gamepad bindings, using defaults: {err}");
+            Self::default()
+        })
+    }
+}
+
+// -- KeyBindingsScreen
+
+/// State of the screen used to rebind keys.
 #[derive(Resource, Default)]
-pub(crate) struct TouchStateRegistry {
-    pub(crate) touch_start: HashMap<u64, TouchState>,
+pub(crate) struct KeyBindingsScreen {
+    pub(crate) open: bool,
+    /// Index of the selected action in `Action::all()`
+    pub(crate) selected: usize,
+    /// The next pressed key will be bound to the selected action
+    pub(crate) capturing: bool,
+}
+
+// -- PauseMenu
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PauseMenuOption {
+    Resume,
+    Restart,
+    MainMenu,
+    Quit,
+}
+
+impl PauseMenuOption {
+    pub(crate) const fn all() -> [Self; 4] {
+        [Self::Resume, Self::Restart, Self::MainMenu, Self::Quit]
+    }
+
+    pub(crate) const fn label(self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Restart => "Restart",
+            Self::MainMenu => "Main menu",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+/// Option selected in the pause menu.
+#[derive(Resource, Default)]
+pub(crate) struct PauseMenu {
+    /// Index of the selected option in `PauseMenuOption::all()`
+    pub(crate) selected: usize,
 }
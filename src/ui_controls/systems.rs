@@ -1,34 +1,489 @@
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
 use bevy::input::keyboard::KeyboardInput;
 use bevy::input::touch::TouchPhase;
 use bevy::input::ButtonState;
+use bevy::window::WindowFocused;
+use std::collections::HashSet;
+use std::time::Duration;
+
 use bevy::prelude::*;
 
+use crate::bot::resources::BotPlayer;
+use crate::common::resources::{ColorPalette, ResColor};
+use crate::game_rules::events::RestartGame;
+use crate::game_rules::resources::{AppState, PlayerInput, PlayerInputQueue};
+use crate::ui_side::resources::FontsCollection;
+
+use super::components::*;
 use super::resources::*;
 
 pub(crate) fn collect_keyboard_presses(
     mut keyboard_input_events: EventReader<KeyboardInput>,
     mut player_input_queue: ResMut<PlayerInputQueue>,
+    mut auto_shift: ResMut<AutoShiftState>,
+    auto_shift_config: Res<AutoShiftConfig>,
+    bindings: Res<KeyBindings>,
+    screen: Res<KeyBindingsScreen>,
+    state: Res<State<AppState>>,
 ) {
+    // Events are still read while the game is paused so that they are not
+    // sent to the game once it resumes
+    if screen.open || *state.get() != AppState::Playing {
+        keyboard_input_events.clear();
+        return;
+    }
+
+    for event in keyboard_input_events.read() {
+        for action in bindings.actions(event.key_code) {
+            match (action, event.state) {
+                // Horizontal moves are handled by `auto_shift`
+                (Action::MoveLeft | Action::MoveRight, _) => {}
+                (Action::SoftDrop, ButtonState::Pressed) => {
+                    player_input_queue.push_back(PlayerInput::SoftDrop)
+                }
+                (Action::SoftDrop, ButtonState::Released) => {
+                    player_input_queue.push_back(PlayerInput::SoftDropRelease)
+                }
+                (_, ButtonState::Pressed) => {
+                    if let Some(input) = action.player_input() {
+                        player_input_queue.push_back(input);
+                        auto_shift.cut = auto_shift_config.dcd;
+                    }
+                }
+                (_, ButtonState::Released) => {}
+            }
+        }
+    }
+}
+
+/// Track actions held on connected gamepads, and send them to the game the
+/// same way keys are.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn collect_gamepad_presses(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<GamepadBindings>,
+    screen: Res<KeyBindingsScreen>,
+    state: Res<State<AppState>>,
+    auto_shift_config: Res<AutoShiftConfig>,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut actions: ResMut<ButtonInput<Action>>,
+    mut auto_shift: ResMut<AutoShiftState>,
+    mut player_input_queue: ResMut<PlayerInputQueue>,
+) {
+    for event in connections.read() {
+        if let GamepadConnection::Connected(info) = &event.connection {
+            let custom = bindings.gamepads.contains_key(&info.name);
+            info!(
+                "Gamepad connected: {} (custom mapping: {custom})",
+                info.name
+            );
+        }
+    }
+
+    let pressed: HashSet<_> = gamepads
+        .iter()
+        .flat_map(|gamepad| {
+            bindings
+                .mapping(gamepads.name(gamepad))
+                .pressed(gamepad, &buttons, &axes)
+        })
+        .collect();
+
+    actions.clear();
+
+    for action in Action::all() {
+        if pressed.contains(&action) {
+            actions.press(action);
+        } else {
+            actions.release(action);
+        }
+    }
+
+    if screen.open || *state.get() != AppState::Playing {
+        return;
+    }
+
+    for &action in actions.get_just_pressed() {
+        match action {
+            // Horizontal moves are handled by `auto_shift`
+            Action::MoveLeft | Action::MoveRight => {}
+            Action::SoftDrop => player_input_queue.push_back(PlayerInput::SoftDrop),
+            _ => {
+                if let Some(input) = action.player_input() {
+                    player_input_queue.push_back(input);
+                    auto_shift.cut = auto_shift_config.dcd;
+                }
+            }
+        }
+    }
+
+    if actions.just_released(Action::SoftDrop) {
+        player_input_queue.push_back(PlayerInput::SoftDropRelease);
+    }
+}
+
+/// Actions that are not sent to the game.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn meta_actions(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepad_actions: Res<ButtonInput<Action>>,
+    bindings: Res<KeyBindings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut screen: ResMut<KeyBindingsScreen>,
+    mut exit: EventWriter<AppExit>,
+    mut restart: EventWriter<RestartGame>,
+    bot: Option<ResMut<BotPlayer>>,
+) {
+    if screen.open {
+        return;
+    }
+
+    let just_pressed =
+        |action| bindings.just_pressed(&keyboard, action) || gamepad_actions.just_pressed(action);
+
+    if just_pressed(Action::Quit) {
+        exit.send(AppExit::Success);
+    }
+
+    if just_pressed(Action::Restart) {
+        restart.send(RestartGame);
+    }
+
+    if just_pressed(Action::Pause) {
+        match state.get() {
+            AppState::Playing => next_state.set(AppState::Paused),
+            AppState::Paused => next_state.set(AppState::Playing),
+            AppState::GameOver => next_state.set(AppState::Menu),
+            AppState::Menu => {}
+        }
+    }
+
+    if let Some(mut bot) = bot.filter(|_| just_pressed(Action::ToggleBot)) {
+        bot.enabled = !bot.enabled;
+    }
+
+    if just_pressed(Action::KeyBindings) {
+        *screen = KeyBindingsScreen {
+            open: true,
+            ..default()
+        };
+
+        // The game stays paused once the screen is closed
+        if *state.get() == AppState::Playing {
+            next_state.set(AppState::Paused);
+        }
+    }
+}
+
+/// Direction that moves the piece given held directions, from first to last
+/// pressed.
+fn auto_shift_direction(held: &[PlayerInput], policy: OppositeDirection) -> Option<PlayerInput> {
+    match policy {
+        OppositeDirection::LastPressed => held.last().copied(),
+        OppositeDirection::FirstPressed => held.first().copied(),
+        OppositeDirection::Cancel if held.len() == 1 => Some(held[0]),
+        OppositeDirection::Cancel => None,
+    }
+}
+
+/// Repeat horizontal moves while a direction is held, without relying on the
+/// key repeat of the OS.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn auto_shift(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepad_actions: Res<ButtonInput<Action>>,
+    bindings: Res<KeyBindings>,
+    screen: Res<KeyBindingsScreen>,
+    app_state: Res<State<AppState>>,
+    config: Res<AutoShiftConfig>,
+    mut state: ResMut<AutoShiftState>,
+    mut player_input_queue: ResMut<PlayerInputQueue>,
+    time: Res<Time>,
+) {
+    if screen.open || *app_state.get() != AppState::Playing {
+        return;
+    }
+
+    for (action, input) in [
+        (Action::MoveLeft, PlayerInput::MoveLeft),
+        (Action::MoveRight, PlayerInput::MoveRight),
+    ] {
+        let pressed = bindings.pressed(&keyboard, action) || gamepad_actions.pressed(action);
+
+        let just_pressed =
+            bindings.just_pressed(&keyboard, action) || gamepad_actions.just_pressed(action);
+
+        // A direction held on both devices only counts once
+        if just_pressed && !state.held.contains(&input) {
+            state.held.push(input);
+        }
+
+        if !pressed {
+            state.held.retain(|&held| held != input);
+        }
+    }
+
+    let active = auto_shift_direction(&state.held, config.opposite);
+
+    if active != state.active {
+        if state.shifting {
+            player_input_queue.push_back(PlayerInput::ShiftRelease);
+            state.shifting = false;
+        }
+
+        if let Some(input) = active {
+            player_input_queue.push_back(input);
+        }
+
+        // DAS cut delay only applies to a direction held through the action
+        state.active = active;
+        state.charge = Duration::ZERO;
+        state.repeats = 0;
+        state.cut = Duration::ZERO;
+        return;
+    }
+
+    // DAS cut delay pauses the charge, it elapses even if no direction is held
+    let mut delta = time.delta();
+    let cut = state.cut.min(delta);
+    state.cut -= cut;
+    delta -= cut;
+
+    let Some(input) = active else {
+        return;
+    };
+
+    if !state.cut.is_zero() {
+        if state.shifting {
+            player_input_queue.push_back(PlayerInput::ShiftRelease);
+            state.shifting = false;
+        }
+
+        return;
+    }
+
+    state.charge += delta;
+
+    let Some(charged) = state.charge.checked_sub(config.das) else {
+        return;
+    };
+
+    if config.arr.is_zero() {
+        if !state.shifting {
+            let shift = match input {
+                PlayerInput::MoveLeft => PlayerInput::ShiftLeft,
+                _ => PlayerInput::ShiftRight,
+            };
+
+            player_input_queue.push_back(shift);
+            state.shifting = true;
+        }
+
+        return;
+    }
+
+    // First repeat happens when DAS is charged, then after each ARR
+    let repeats = 1 + u32::try_from(charged.as_nanos() / config.arr.as_nanos()).unwrap_or(u32::MAX);
+
+    while state.repeats < repeats {
+        player_input_queue.push_back(input);
+        state.repeats += 1;
+    }
+}
+
+// -- Pause menu
+
+/// Pause the game when the window loses focus.
+pub(crate) fn pause_on_focus_loss(
+    mut focus_events: EventReader<WindowFocused>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if focus_events.read().any(|event| !event.focused) && *state.get() == AppState::Playing {
+        next_state.set(AppState::Paused);
+    }
+}
+
+pub(crate) fn pause_menu_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    screen: Res<KeyBindingsScreen>,
+    mut menu: ResMut<PauseMenu>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+    mut restart: EventWriter<RestartGame>,
+) {
+    if screen.open {
+        return;
+    }
+
+    let options = PauseMenuOption::all();
+
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = menu.selected.checked_sub(1).unwrap_or(options.len() - 1);
+    }
+
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % options.len();
+    }
+
+    if keyboard.just_pressed(KeyCode::Enter) {
+        match options[menu.selected] {
+            PauseMenuOption::Resume => next_state.set(AppState::Playing),
+            PauseMenuOption::Restart => {
+                restart.send(RestartGame);
+            }
+            PauseMenuOption::MainMenu => next_state.set(AppState::Menu),
+            PauseMenuOption::Quit => {
+                exit.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+pub(crate) fn reset_pause_menu(mut menu: ResMut<PauseMenu>) {
+    *menu = PauseMenu::default();
+}
+
+/// Directions held before the pause are forgotten, the game then resumes
+/// from a neutral state.
+pub(crate) fn reset_auto_shift(mut state: ResMut<AutoShiftState>) {
+    *state = AutoShiftState::default();
+}
+
+// -- Key bindings screen
+
+/// Navigate the key bindings screen and rebind keys.
+pub(crate) fn key_bindings_screen_input(
+    mut keyboard_input_events: EventReader<KeyboardInput>,
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut screen: ResMut<KeyBindingsScreen>,
+) {
+    // Events are still read while the screen is closed so that the key that
+    // opened it is not handled again
+    if !screen.open {
+        keyboard_input_events.clear();
+        return;
+    }
+
     for event in keyboard_input_events.read() {
         if event.state != ButtonState::Pressed {
             continue;
         }
 
-        match &event.key_code {
-            KeyCode::ArrowLeft => player_input_queue.push_back(PlayerInput::MoveLeft),
-            KeyCode::ArrowRight => player_input_queue.push_back(PlayerInput::MoveRight),
-            KeyCode::ArrowUp | KeyCode::KeyX => {
-                player_input_queue.push_back(PlayerInput::RotateRight)
+        let key = event.key_code;
+        let action = Action::all()[screen.selected];
+
+        if screen.capturing {
+            if key != KeyCode::Escape {
+                bindings.bind(action, key);
+                bindings.save();
+            }
+
+            screen.capturing = false;
+            continue;
+        }
+
+        match key {
+            KeyCode::ArrowUp => {
+                screen.selected = screen
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(Action::all().len() - 1)
             }
-            KeyCode::ControlLeft | KeyCode::ControlRight | KeyCode::KeyZ => {
-                player_input_queue.push_back(PlayerInput::RotateLeft)
+            KeyCode::ArrowDown => screen.selected = (screen.selected + 1) % Action::all().len(),
+            KeyCode::Enter => screen.capturing = true,
+            KeyCode::Backspace => {
+                bindings.keys[action] = KeyBindings::default().keys[action].clone();
+                bindings.save();
+            }
+            _ if key == KeyCode::Escape
+                || bindings.actions(key).any(|a| a == Action::KeyBindings) =>
+            {
+                screen.open = false;
+
+                // The key must not trigger a meta action once the screen is closed
+                keyboard.clear_just_pressed(key);
             }
-            KeyCode::Space => player_input_queue.push_back(PlayerInput::HardDrop),
             _ => {}
         }
     }
 }
 
+pub(crate) fn draw_key_bindings_screen(
+    mut commands: Commands,
+    bindings: Res<KeyBindings>,
+    screen: Res<KeyBindingsScreen>,
+    fonts: Res<FontsCollection>,
+    palette: Res<ColorPalette>,
+    overlays: Query<Entity, With<KeyBindingsOverlay>>,
+) {
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !screen.open {
+        return;
+    }
+
+    let text_style = |color: &ResColor| TextStyle {
+        font_size: 20.0,
+        color: color.color,
+        font: fonts.default.clone(),
+    };
+
+    let mut sections = vec![TextSection::new(
+        "Key bindings\n\n",
+        TextStyle {
+            font_size: 32.0,
+            color: palette.text_title.color,
+            font: fonts.title.clone(),
+        },
+    )];
+
+    for (i, action) in Action::all().into_iter().enumerate() {
+        let (color, keys) = match (i == screen.selected, screen.capturing) {
+            (true, true) => (&palette.text_title, "press a key".to_string()),
+            (true, false) => (&palette.text_title, bindings.describe(action)),
+            (false, _) => (&palette.text_default, bindings.describe(action)),
+        };
+
+        sections.push(TextSection::new(
+            format!("{:<14}{keys}\n", action.label()),
+            text_style(color),
+        ));
+    }
+
+    sections.push(TextSection::new(
+        "\nUp/Down: select - Enter: rebind\nBackspace: reset - Escape: close",
+        text_style(&palette.text_default),
+    ));
+
+    commands
+        .spawn((
+            Name::new("Key Bindings Overlay"),
+            KeyBindingsOverlay,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: palette.background_1.color.with_alpha(0.95).into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections(sections));
+        });
+}
+
 pub(crate) fn debug_touchscreen(mut touch_events: EventReader<TouchInput>) {
     for event in touch_events.read() {
         info!("{event:?}")
@@ -54,11 +509,21 @@ pub(crate) fn touch_start(
 
 pub(crate) fn touch_end(
     mut touch_events: EventReader<TouchInput>,
+    mut player_input_queue: ResMut<PlayerInputQueue>,
     mut touch_state: ResMut<TouchStateRegistry>,
 ) {
     for event in touch_events.read() {
-        if matches!(event.phase, TouchPhase::Ended | TouchPhase::Canceled) {
-            touch_state.touch_start.remove(&event.id);
+        if !matches!(event.phase, TouchPhase::Ended | TouchPhase::Canceled) {
+            continue;
+        }
+
+        let Some(state) = touch_state.touch_start.remove(&event.id) else {
+            continue;
+        };
+
+        // A simple tap that didn't draw any move triggers hold
+        if event.phase == TouchPhase::Ended && state.drawn_input.is_none() {
+            player_input_queue.push_back(PlayerInput::Hold);
         }
     }
 }
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::game_rules::components::GridPos;
+
 #[derive(Component)]
 pub(crate) struct PieceTile;
 
@@ -10,6 +12,19 @@ pub(crate) struct PieceGhost(pub(crate) Entity);
 #[derive(Component)]
 pub(crate) struct OneShotPlayer;
 
+/// Position of a falling piece at the previous game tick, its sprite moves
+/// from there to the current position until the next tick.
+#[derive(Component, Clone, Copy)]
+pub(crate) struct PreviousGridPos(pub(crate) GridPos);
+
 /// Marker that indicate when a sprite is aligned from the center of cells.
 #[derive(Component)]
 pub(crate) struct AlignedOnCellCenter;
+
+/// Panel displayed on top of the grid once the game is over.
+#[derive(Component)]
+pub(crate) struct GameOverOverlay;
+
+/// Panel hiding the grid while the game is paused.
+#[derive(Component)]
+pub(crate) struct PauseOverlay;
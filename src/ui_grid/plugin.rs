@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 
 use crate::game_rules::plugin::GameUpdateSystems;
-use crate::game_rules::resources::PausedForClear;
+use crate::game_rules::resources::{AppState, PausedForClear};
+use crate::ui_controls::resources::PauseMenu;
 
 use super::resources::*;
 use super::systems::*;
@@ -18,17 +19,27 @@ impl Plugin for UiGridPlugin {
             size: self.size,
         });
 
-        app.init_resource::<MeshCollection>()
+        app.init_resource::<GridLayout>()
+            .init_resource::<MeshCollection>()
             .init_resource::<AnimationCollection>()
             .init_resource::<UiGridRoot>()
             .add_systems(
                 Startup,
                 (setup_camera, draw_background_grid, draw_frame).chain(),
             )
+            .add_systems(FixedUpdate, store_previous_pos.before(GameUpdateSystems))
+            .add_systems(OnExit(AppState::GameOver), despawn_game_over_overlay)
+            .add_systems(OnExit(AppState::Paused), despawn_pause_overlay)
+            // Game rules run in `FixedUpdate`, which always completes before `Update`: sprites
+            // snap to the latest simulated state, apart from the falling piece which is
+            // interpolated between the last two ticks.
             .add_systems(
                 Update,
                 (
-                    button_pressed,
+                    spawn_game_over_overlay,
+                    draw_pause_overlay
+                        .run_if(in_state(AppState::Paused))
+                        .run_if(resource_changed::<PauseMenu>),
                     (
                         // Ghost
                         (attach_piece_ghost, remove_hanging_piece_ghost),
@@ -40,14 +51,14 @@ impl Plugin for UiGridPlugin {
                         attach_filled_cell_sprite,
                         // Generic transforms
                         apply_sprite_pos,
+                        interpolate_piece_pos,
                         apply_sprite_angle,
                         // Clear line animation
                         start_clear_line_animation.run_if(resource_added::<PausedForClear>),
                         // Cleanup
                         cleanup_finished_oneshot_players,
                     )
-                        .chain()
-                        .after(GameUpdateSystems),
+                        .chain(),
                 ),
             );
     }
@@ -4,19 +4,23 @@ use bevy::animation::AnimationTargetId;
 use bevy::prelude::*;
 use enum_map::EnumMap;
 
+use tetris_core::board::{BoardConfig, GRID_WIDTH};
+
 use crate::game_rules::components::PieceKind;
-use crate::{GRID_VISIBLE_HEIGHT, GRID_WIDTH};
 
 // Shape of the area
 pub(crate) const UI_GRID_VIRTUAL_HEIGHT: f32 = 800.0;
 pub(crate) const UI_GRID_VIRTUAL_WIDTH: f32 = 400.0;
 
 // Size of elements
-pub(crate) const CELL_SIZE: f32 = (UI_GRID_VIRTUAL_WIDTH - BORDER_SIZE) / 10.0;
 pub(crate) const BORDER_SIZE: f32 = 20.0;
 pub(crate) const BLOCK_SQUARE_RATIO: f32 = 0.9;
 pub(crate) const BLOCK_SQUARE_SMALL_RATIO: f32 = 0.75;
 
+/// Size of cells of a guideline board, which is kept for pieces displayed
+/// outside of the grid.
+pub(crate) const CELL_SIZE: f32 = (UI_GRID_VIRTUAL_WIDTH - BORDER_SIZE) / GRID_WIDTH as f32;
+
 // Config
 
 #[derive(Resource)]
@@ -25,6 +29,52 @@ pub(crate) struct UiGridConfig {
     pub(crate) size: [f32; 2],
 }
 
+// -- Layout
+
+/// Placement of the cells in the area, which depends on the dimensions of the
+/// board.
+#[derive(Resource, Clone, Copy, Debug)]
+pub(crate) struct GridLayout {
+    pub(crate) board: BoardConfig,
+    pub(crate) cell_size: f32,
+}
+
+impl FromWorld for GridLayout {
+    fn from_world(world: &mut World) -> Self {
+        let board = world.get_resource_or_insert_with(BoardConfig::default);
+        Self::new(*board)
+    }
+}
+
+impl GridLayout {
+    pub(crate) fn new(board: BoardConfig) -> Self {
+        // Cells are as large as possible while leaving room for the frame and
+        // a glimpse of the buffer zone
+        let cell_size = f32::min(
+            (UI_GRID_VIRTUAL_WIDTH - BORDER_SIZE) / f32::from(board.width),
+            (UI_GRID_VIRTUAL_HEIGHT - 2.0 * BORDER_SIZE) / f32::from(board.visible_height),
+        );
+
+        Self { board, cell_size }
+    }
+
+    /// Size of the visible part of the grid, without its frame.
+    pub(crate) fn size(&self) -> Vec2 {
+        self.cell_size * Vec2::new(self.board.width.into(), self.board.visible_height.into())
+    }
+
+    /// Center of a cell, the grid is centered horizontally and sits at the
+    /// bottom of the area.
+    pub(crate) fn tile_translation(&self, x: u8, y: u8, z: f32) -> Vec3 {
+        Vec3::new(
+            self.cell_size * (f32::from(x) + 0.5) - self.size().x / 2.0,
+            self.cell_size * (f32::from(y) + 0.5) + BORDER_SIZE / 2.0
+                - UI_GRID_VIRTUAL_HEIGHT / 2.0,
+            z,
+        )
+    }
+}
+
 // -- Root
 #[derive(Resource)]
 pub(crate) struct UiGridRoot(Entity);
@@ -72,22 +122,29 @@ pub(crate) struct MeshCollection {
     pub(crate) grid: Handle<Mesh>,
     pub(crate) grid_background: Handle<Mesh>,
     pub(crate) pieces_small_blocks: EnumMap<PieceKind, Handle<Mesh>>,
+    /// Same as `pieces_small_blocks` with the size of a guideline board
+    pub(crate) pieces_preview_blocks: EnumMap<PieceKind, Handle<Mesh>>,
 }
 
 impl FromWorld for MeshCollection {
     fn from_world(world: &mut World) -> Self {
+        let layout = *world.resource::<GridLayout>();
+        let cell_size = layout.cell_size;
+
+        // Width of the grid including its frame
+        let frame_width = layout.size().x + BORDER_SIZE;
+
         let frame = {
             let vertical_bar = Rectangle::new(BORDER_SIZE / 2.0, UI_GRID_VIRTUAL_HEIGHT);
 
-            let horizontal_bar = Rectangle::new(UI_GRID_VIRTUAL_WIDTH, BORDER_SIZE / 2.0);
+            let horizontal_bar = Rectangle::new(frame_width, BORDER_SIZE / 2.0);
 
             let mut mesh: Mesh = Mesh::from(vertical_bar)
-                .translated_by([BORDER_SIZE / 4.0 - UI_GRID_VIRTUAL_WIDTH / 2.0, 0.0, 0.0].into());
+                .translated_by([BORDER_SIZE / 4.0 - frame_width / 2.0, 0.0, 0.0].into());
 
             mesh.merge(
-                &Mesh::from(vertical_bar).translated_by(
-                    [UI_GRID_VIRTUAL_WIDTH / 2.0 - BORDER_SIZE / 4.0, 0.0, 0.0].into(),
-                ),
+                &Mesh::from(vertical_bar)
+                    .translated_by([frame_width / 2.0 - BORDER_SIZE / 4.0, 0.0, 0.0].into()),
             );
 
             mesh.merge(&Mesh::from(horizontal_bar).translated_by(
@@ -103,13 +160,14 @@ impl FromWorld for MeshCollection {
 
         fn mesh_piece(
             coords: impl Iterator<Item = [i8; 2]>,
+            cell_size: f32,
             square_size: f32,
             align_on_cell_center: bool,
         ) -> Mesh {
             coords
                 .map(|[x, y]| {
-                    Mesh::from(Rectangle::from_length(CELL_SIZE * square_size)).translated_by(
-                        [CELL_SIZE * f32::from(x), CELL_SIZE * f32::from(y), 0.0].into(),
+                    Mesh::from(Rectangle::from_length(cell_size * square_size)).translated_by(
+                        [cell_size * f32::from(x), cell_size * f32::from(y), 0.0].into(),
                     )
                 })
                 .reduce(|mut x, y| {
@@ -119,46 +177,52 @@ impl FromWorld for MeshCollection {
                 .unwrap()
                 .translated_by({
                     if align_on_cell_center {
-                        [0.5 * CELL_SIZE, 0.5 * CELL_SIZE, 0.0].into()
+                        [0.5 * cell_size, 0.5 * cell_size, 0.0].into()
                     } else {
                         [0.0, 0.0, 0.0].into()
                     }
                 })
         }
 
-        let grid_background = world.add_asset(Rectangle::new(
-            UI_GRID_VIRTUAL_WIDTH,
-            UI_GRID_VIRTUAL_HEIGHT,
-        ));
+        let grid_background = world.add_asset(Rectangle::new(frame_width, UI_GRID_VIRTUAL_HEIGHT));
+
+        let BoardConfig {
+            width,
+            visible_height,
+            ..
+        } = layout.board;
 
         let grid = world.add_asset(
             mesh_piece(
-                (0..GRID_WIDTH)
-                    .flat_map(|x| (0..GRID_VISIBLE_HEIGHT).map(move |y| [x as _, y as _])),
+                (0..width).flat_map(|x| (0..visible_height).map(move |y| [x as _, y as _])),
+                cell_size,
                 0.1,
                 false,
             )
-            .translated_by(Vec3::new(
-                (BORDER_SIZE - UI_GRID_VIRTUAL_WIDTH + CELL_SIZE) / 2.0,
-                (BORDER_SIZE - UI_GRID_VIRTUAL_HEIGHT + CELL_SIZE) / 2.0,
-                0.0,
-            )),
+            .translated_by(layout.tile_translation(0, 0, 0.0)),
         );
 
-        let pieces_small_blocks = EnumMap::from_fn(|piece_kind: PieceKind| {
-            world.add_asset(mesh_piece(
-                piece_kind.base_shape().into_iter(),
-                BLOCK_SQUARE_SMALL_RATIO,
-                piece_kind.base_width() % 2 == 0,
-            ))
-        });
+        let mut pieces_blocks = |cell_size| {
+            EnumMap::from_fn(|piece_kind: PieceKind| {
+                world.add_asset(mesh_piece(
+                    piece_kind.base_shape().into_iter(),
+                    cell_size,
+                    BLOCK_SQUARE_SMALL_RATIO,
+                    piece_kind.base_width() % 2 == 0,
+                ))
+            })
+        };
+
+        let pieces_small_blocks = pieces_blocks(cell_size);
+        let pieces_preview_blocks = pieces_blocks(CELL_SIZE);
 
         Self {
-            square: world.add_asset(Rectangle::from_length(CELL_SIZE * BLOCK_SQUARE_RATIO)),
+            square: world.add_asset(Rectangle::from_length(cell_size * BLOCK_SQUARE_RATIO)),
             frame,
             grid,
             grid_background,
             pieces_small_blocks,
+            pieces_preview_blocks,
         }
     }
 }
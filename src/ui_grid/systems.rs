@@ -1,17 +1,19 @@
 use bevy::animation::AnimationTarget;
-use bevy::input::keyboard::{Key, KeyboardInput};
 use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
 use bevy::sprite::MaterialMesh2dBundle;
+use bevy::text::Text2dBounds;
 
 use crate::common::resources::ColorPalette;
 use crate::game_rules::components::{Fall, FilledCell, GridPos, PieceKind, Spin};
-use crate::game_rules::resources::{GridState, PausedForClear};
+use crate::game_rules::events::{GameEnd, GameOver};
+use crate::game_rules::resources::{GridState, PausedForClear, Score};
+use crate::ui_controls::resources::{Action, KeyBindings, PauseMenu, PauseMenuOption};
+use crate::ui_side::resources::FontsCollection;
 use crate::WINDOW_SIZE;
 
 use super::components::*;
 use super::resources::*;
-use super::tile_translation;
 
 // -- Camera
 
@@ -41,6 +43,8 @@ pub(crate) fn draw_frame(
             MaterialMesh2dBundle {
                 mesh: meshes.frame.clone().into(),
                 material: palette.background_2.material.clone(),
+                // Pieces in the buffer zone are partially hidden by the frame
+                transform: Transform::from_translation([0.0, 0.0, 200.0].into()),
                 ..Default::default()
             },
         ))
@@ -83,6 +87,7 @@ pub(crate) fn attach_filled_cell_sprite(
     root: Res<UiGridRoot>,
     palette: Res<ColorPalette>,
     meshes: Res<MeshCollection>,
+    layout: Res<GridLayout>,
     newly_filled_cells: Query<(Entity, &GridPos, &FilledCell), Added<FilledCell>>,
     animations: Res<AnimationCollection>,
 ) {
@@ -109,7 +114,7 @@ pub(crate) fn attach_filled_cell_sprite(
                 MaterialMesh2dBundle {
                     mesh: meshes.square.clone().into(),
                     transform: Transform::default()
-                        .with_translation(tile_translation(pos.x, pos.y, 0.0)),
+                        .with_translation(layout.tile_translation(pos.x, pos.y, 0.0)),
                     material: palette.pieces[filled.color_from_kind].material.clone(),
                     ..Default::default()
                 },
@@ -174,13 +179,14 @@ pub(crate) fn attach_piece_sprite(
     root: Res<UiGridRoot>,
     palette: Res<ColorPalette>,
     meshes: Res<MeshCollection>,
-    pieces: Query<(Entity, &PieceKind), Added<Fall>>,
+    pieces: Query<(Entity, &PieceKind, &GridPos), Added<Fall>>,
 ) {
-    for (entity, &kind) in &pieces {
+    for (entity, &kind, &pos) in &pieces {
         let mut cmd = commands.entity(entity);
 
         cmd.insert((
             PieceTile,
+            PreviousGridPos(pos),
             MaterialMesh2dBundle {
                 mesh: meshes.pieces_small_blocks[kind].clone().into(),
                 material: palette.pieces[kind].material.clone(),
@@ -276,19 +282,65 @@ pub(crate) fn update_ghost_spin(
 
 // -- Update transformations
 
+fn sprite_translation(
+    layout: &GridLayout,
+    pos: GridPos,
+    aligned_on_cell_center: bool,
+    z: f32,
+) -> Vec3 {
+    let mut translation = layout.tile_translation(pos.x, pos.y, z);
+
+    if aligned_on_cell_center {
+        let offset = -0.5 * layout.cell_size;
+        translation += Vec3::new(offset, offset, 0.0);
+    }
+
+    translation
+}
+
 #[allow(clippy::type_complexity)]
 pub(crate) fn apply_sprite_pos(
     mut pieces: Query<
         (&GridPos, Has<AlignedOnCellCenter>, &mut Transform),
-        Or<(Added<Transform>, Changed<GridPos>)>,
+        (
+            Without<PreviousGridPos>,
+            Or<(Added<Transform>, Changed<GridPos>)>,
+        ),
     >,
+    layout: Res<GridLayout>,
 ) {
-    for (pos, aligned_on_cell_center, mut transform) in &mut pieces {
-        transform.translation = tile_translation(pos.x, pos.y, transform.translation.z);
+    for (&pos, aligned_on_cell_center, mut transform) in &mut pieces {
+        let z = transform.translation.z;
+        transform.translation = sprite_translation(&layout, pos, aligned_on_cell_center, z);
+    }
+}
 
-        if aligned_on_cell_center {
-            transform.translation += Vec3::new(-0.5 * CELL_SIZE, -0.5 * CELL_SIZE, 0.0);
-        }
+/// Runs before each game tick, in `FixedUpdate`.
+pub(crate) fn store_previous_pos(mut pieces: Query<(&GridPos, &mut PreviousGridPos)>) {
+    for (&pos, mut previous) in &mut pieces {
+        previous.0 = pos;
+    }
+}
+
+/// Several frames can be rendered during a game tick, falling pieces move
+/// smoothly between their positions of the last two ticks.
+pub(crate) fn interpolate_piece_pos(
+    time: Res<Time<Fixed>>,
+    layout: Res<GridLayout>,
+    mut pieces: Query<(
+        &GridPos,
+        &PreviousGridPos,
+        Has<AlignedOnCellCenter>,
+        &mut Transform,
+    )>,
+) {
+    let progress = time.overstep_fraction();
+
+    for (&pos, &PreviousGridPos(previous), aligned_on_cell_center, mut transform) in &mut pieces {
+        let z = transform.translation.z;
+        let from = sprite_translation(&layout, previous, aligned_on_cell_center, z);
+        let to = sprite_translation(&layout, pos, aligned_on_cell_center, z);
+        transform.translation = from.lerp(to, progress);
     }
 }
 
@@ -298,19 +350,198 @@ pub(crate) fn apply_sprite_angle(mut pieces: Query<(&Spin, &mut Transform), Chan
     }
 }
 
-// Window controls
-// TODO: they might deserve their own plugin
+// -- Game over
 
-pub(crate) fn button_pressed(
-    mut keyboard_input_events: EventReader<KeyboardInput>,
-    mut exit: EventWriter<AppExit>,
+pub(crate) fn spawn_game_over_overlay(
+    mut commands: Commands,
+    mut game_over: EventReader<GameOver>,
+    root: Res<UiGridRoot>,
+    fonts: Res<FontsCollection>,
+    meshes: Res<MeshCollection>,
+    palette: Res<ColorPalette>,
+    bindings: Res<KeyBindings>,
 ) {
-    for event in keyboard_input_events.read() {
-        match &event.logical_key {
-            Key::Character(c) if ["q", "Q"].contains(&c.as_str()) => {
-                exit.send(AppExit::Success);
-            }
-            _ => {}
-        }
+    let Some(game_over) = game_over.read().last() else {
+        return;
+    };
+
+    let (title, results) = match game_over.cause {
+        GameEnd::GoalReached if game_over.xp.line_goal.is_some() => (
+            "Finished",
+            format!(
+                "Time: {}\nPieces: {}\nPPS: {:.2}\nKPP: {:.2}",
+                game_over.stopwatch,
+                game_over.stats.pieces,
+                game_over.stats.pps(&game_over.stopwatch),
+                game_over.stats.kpp(),
+            ),
+        ),
+        GameEnd::TimeUp => (
+            "Time Up",
+            game_over
+                .stats
+                .points
+                .iter()
+                .map(|(source, &points)| format!("\n{}: {}", source.label(), Score(points)))
+                .fold(format!("Score: {}\n", game_over.score), |acc, line| {
+                    acc + &line
+                }),
+        ),
+        GameEnd::GoalReached | GameEnd::TopOut(_) => (
+            if game_over.cause == GameEnd::GoalReached {
+                "Finished"
+            } else {
+                "Game Over"
+            },
+            format!(
+                "Score: {}\nLevel: {}\nTime: {}",
+                game_over.score, game_over.xp, game_over.stopwatch,
+            ),
+        ),
+    };
+
+    // Background fits the text, which is 7 rows long for a regular game over
+    let rows = results.lines().count() + 4;
+    let height = 0.3 * rows.max(7) as f32 / 7.0;
+
+    let title_style = TextStyle {
+        font_size: 48.0,
+        color: palette.text_title.color,
+        font: fonts.title.clone(),
+    };
+
+    let text_style = TextStyle {
+        font_size: 24.0,
+        color: palette.text_default.color,
+        font: fonts.default.clone(),
+    };
+
+    commands
+        .spawn((
+            Name::new("Game Over Overlay"),
+            GameOverOverlay,
+            MaterialMesh2dBundle {
+                mesh: meshes.grid_background.clone().into(),
+                material: palette.background_1.material.clone(),
+                transform: Transform::from_translation([0.0, 0.0, 300.0].into())
+                    .with_scale(Vec3::new(0.8, height, 1.0)),
+                ..Default::default()
+            },
+        ))
+        .set_parent(**root);
+
+    commands
+        .spawn((
+            Name::new("Game Over Text"),
+            GameOverOverlay,
+            Text2dBundle {
+                text: Text::from_sections([
+                    TextSection::new(format!("{title}\n\n"), title_style),
+                    TextSection::new(
+                        format!(
+                            "{results}\n\nPress {} to restart\nPress {} for menu",
+                            bindings.describe(Action::Restart),
+                            bindings.describe(Action::Pause),
+                        ),
+                        text_style,
+                    ),
+                ])
+                .with_justify(JustifyText::Center),
+                text_2d_bounds: Text2dBounds {
+                    size: Vec2::new(UI_GRID_VIRTUAL_WIDTH, UI_GRID_VIRTUAL_HEIGHT),
+                },
+                transform: Transform::from_translation([0.0, 0.0, 310.0].into()),
+                ..Default::default()
+            },
+        ))
+        .set_parent(**root);
+}
+
+pub(crate) fn despawn_game_over_overlay(
+    mut commands: Commands,
+    overlays: Query<Entity, With<GameOverOverlay>>,
+) {
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// -- Pause
+
+pub(crate) fn draw_pause_overlay(
+    mut commands: Commands,
+    menu: Res<PauseMenu>,
+    root: Res<UiGridRoot>,
+    fonts: Res<FontsCollection>,
+    meshes: Res<MeshCollection>,
+    palette: Res<ColorPalette>,
+    overlays: Query<Entity, With<PauseOverlay>>,
+) {
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mut sections = vec![TextSection::new(
+        "Paused\n\n",
+        TextStyle {
+            font_size: 48.0,
+            color: palette.text_title.color,
+            font: fonts.title.clone(),
+        },
+    )];
+
+    for (i, option) in PauseMenuOption::all().into_iter().enumerate() {
+        let (color, label) = if i == menu.selected {
+            (&palette.text_title, format!("> {} <\n", option.label()))
+        } else {
+            (&palette.text_default, format!("{}\n", option.label()))
+        };
+
+        sections.push(TextSection::new(
+            label,
+            TextStyle {
+                font_size: 24.0,
+                color: color.color,
+                font: fonts.default.clone(),
+            },
+        ));
+    }
+
+    // The board is hidden so that the game can't be studied while paused
+    commands
+        .spawn((
+            Name::new("Pause Overlay"),
+            PauseOverlay,
+            MaterialMesh2dBundle {
+                mesh: meshes.grid_background.clone().into(),
+                material: palette.background_1.material.clone(),
+                transform: Transform::from_translation([0.0, 0.0, 300.0].into()),
+                ..Default::default()
+            },
+        ))
+        .set_parent(**root);
+
+    commands
+        .spawn((
+            Name::new("Pause Text"),
+            PauseOverlay,
+            Text2dBundle {
+                text: Text::from_sections(sections).with_justify(JustifyText::Center),
+                text_2d_bounds: Text2dBounds {
+                    size: Vec2::new(UI_GRID_VIRTUAL_WIDTH, UI_GRID_VIRTUAL_HEIGHT),
+                },
+                transform: Transform::from_translation([0.0, 0.0, 310.0].into()),
+                ..Default::default()
+            },
+        ))
+        .set_parent(**root);
+}
+
+pub(crate) fn despawn_pause_overlay(
+    mut commands: Commands,
+    overlays: Query<Entity, With<PauseOverlay>>,
+) {
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
     }
 }
@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Start screen, displayed on top of everything else.
+#[derive(Component)]
+pub(crate) struct MainMenuOverlay;
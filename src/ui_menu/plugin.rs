@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+use crate::game_rules::resources::AppState;
+
+use super::resources::*;
+use super::systems::*;
+
+pub(crate) struct UiMenuPlugin {
+    /// Start on the menu instead of launching a game right away
+    pub(crate) open_at_startup: bool,
+}
+
+impl Plugin for UiMenuPlugin {
+    fn build(&self, app: &mut App) {
+        if self.open_at_startup {
+            app.insert_state(AppState::Menu);
+        }
+
+        app.init_resource::<MainMenu>()
+            .insert_resource(BestScores::load())
+            .add_systems(OnEnter(AppState::Menu), open_main_menu)
+            .add_systems(OnExit(AppState::Menu), despawn_main_menu)
+            .add_systems(
+                Update,
+                (
+                    record_best_score,
+                    (main_menu_input, draw_main_menu)
+                        .chain()
+                        .run_if(in_state(AppState::Menu)),
+                ),
+            );
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::common::storage;
+use crate::game_rules::resources::GameMode;
+
+/// Highest level a game can start at.
+pub(crate) const MAX_START_LEVEL: u32 = 15;
+
+/// Name of the file best scores are saved to.
+const BEST_SCORES_FILE: &str = "best-scores.ron";
+
+// -- MainMenu
+
+/// Rows of the main menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MenuItem {
+    Mode,
+    Level,
+    Randomizer,
+    Handling,
+    Start,
+}
+
+impl MenuItem {
+    pub(crate) const fn all() -> [Self; 5] {
+        [
+            Self::Mode,
+            Self::Level,
+            Self::Randomizer,
+            Self::Handling,
+            Self::Start,
+        ]
+    }
+
+    pub(crate) const fn label(self) -> &'static str {
+        match self {
+            Self::Mode => "Mode",
+            Self::Level => "Level",
+            Self::Randomizer => "Randomizer",
+            Self::Handling => "Handling",
+            Self::Start => "Start",
+        }
+    }
+}
+
+/// Row selected in the main menu, chosen settings are directly written to
+/// the resources they apply to.
+#[derive(Resource, Default)]
+pub(crate) struct MainMenu {
+    /// Index of the selected row in `MenuItem::all()`
+    pub(crate) selected: usize,
+}
+
+// -- BestScores
+
+/// Best result for each game mode: points for a marathon and completion
+/// time in milliseconds for a sprint.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct BestScores(pub(crate) BTreeMap<GameMode, u64>);
+
+impl BestScores {
+    /// Load scores saved by previous games.
+    pub(crate) fn load() -> Self {
+        let Some(data) = storage::read(BEST_SCORES_FILE) else {
+            return Self::default();
+        };
+
+        ron::from_str(&data).map(Self).unwrap_or_else(|err| {
+            warn!("Invalid best scores, starting from scratch: {err}");
+            Self::default()
+        })
+    }
+
+    /// Register the score of a finished game, returns true if it is a new
+    /// best score.
+    pub(crate) fn register(&mut self, mode: GameMode, score: u64) -> bool {
+        // Sprints are won by the fastest time
+        let lower_is_better = mode.line_goal().is_some();
+
+        match self.0.get(&mode) {
+            Some(&best) if lower_is_better && score >= best => return false,
+            Some(&best) if !lower_is_better && score <= best => return false,
+            _ => {}
+        }
+
+        self.0.insert(mode, score);
+        true
+    }
+
+    pub(crate) fn save(&self) {
+        let data = ron::ser::to_string_pretty(&self.0, ron::ser::PrettyConfig::default())
+            .expect("best scores should serialize");
+
+        if let Err(err) = storage::write(BEST_SCORES_FILE, &data) {
+            warn!("Could not save best scores: {err}");
+        }
+    }
+}
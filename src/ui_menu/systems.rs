@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use tetris_core::randomizer::RandomizerKind;
+
+use crate::common::resources::ColorPalette;
+use crate::game_rules::events::{GameEnd, GameOver, RestartGame};
+use crate::game_rules::resources::{GameMode, GameSettings, RandomizerConfig, Score, Stopwatch};
+use crate::ui_controls::resources::{Action, AutoShiftConfig, HandlingProfile, KeyBindingsScreen};
+use crate::ui_side::resources::FontsCollection;
+
+use super::components::*;
+use super::resources::*;
+
+/// Step to the previous or next element of a list, from any value that is
+/// not part of it.
+fn cycle<T: PartialEq + Clone>(values: &[T], current: &T, step: isize) -> T {
+    let len = values.len() as isize;
+
+    let index = match values.iter().position(|value| value == current) {
+        Some(index) => (index as isize + step).rem_euclid(len),
+        None => 0,
+    };
+
+    values[index as usize].clone()
+}
+
+pub(crate) fn open_main_menu(mut menu: ResMut<MainMenu>) {
+    *menu = MainMenu::default();
+}
+
+/// Navigate the main menu with arrows or the D-pad, left and right change
+/// the selected setting.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn main_menu_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepad_actions: Res<ButtonInput<Action>>,
+    screen: Res<KeyBindingsScreen>,
+    mut menu: ResMut<MainMenu>,
+    mut settings: ResMut<GameSettings>,
+    mut randomizer: ResMut<RandomizerConfig>,
+    mut auto_shift: ResMut<AutoShiftConfig>,
+    mut restart: EventWriter<RestartGame>,
+) {
+    if screen.open {
+        return;
+    }
+
+    let items = MenuItem::all();
+    let pressed = |key, action| keyboard.just_pressed(key) || gamepad_actions.just_pressed(action);
+
+    if pressed(KeyCode::ArrowUp, Action::HardDrop) {
+        menu.selected = menu.selected.checked_sub(1).unwrap_or(items.len() - 1);
+    }
+
+    if pressed(KeyCode::ArrowDown, Action::SoftDrop) {
+        menu.selected = (menu.selected + 1) % items.len();
+    }
+
+    let step = {
+        if pressed(KeyCode::ArrowLeft, Action::MoveLeft) {
+            -1
+        } else if pressed(KeyCode::ArrowRight, Action::MoveRight) {
+            1
+        } else {
+            0
+        }
+    };
+
+    if step != 0 {
+        match items[menu.selected] {
+            MenuItem::Mode => settings.mode = cycle(&GameMode::all(), &settings.mode, step),
+            MenuItem::Level => {
+                settings.start_level = settings
+                    .start_level
+                    .saturating_add_signed(step as i32)
+                    .clamp(1, MAX_START_LEVEL)
+            }
+            MenuItem::Randomizer => {
+                randomizer.kind = cycle(&RandomizerKind::presets(), &randomizer.kind, step)
+            }
+            MenuItem::Handling => {
+                let profile = auto_shift.profile().unwrap_or(HandlingProfile::Standard);
+                auto_shift.set_profile(cycle(&HandlingProfile::all(), &profile, step));
+            }
+            MenuItem::Start => {}
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Enter) || gamepad_actions.just_pressed(Action::Pause) {
+        restart.send(RestartGame);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_main_menu(
+    mut commands: Commands,
+    menu: Res<MainMenu>,
+    settings: Res<GameSettings>,
+    randomizer: Res<RandomizerConfig>,
+    auto_shift: Res<AutoShiftConfig>,
+    best_scores: Res<BestScores>,
+    fonts: Res<FontsCollection>,
+    palette: Res<ColorPalette>,
+    overlays: Query<Entity, With<MainMenuOverlay>>,
+) {
+    let changed = menu.is_changed()
+        || settings.is_changed()
+        || randomizer.is_changed()
+        || auto_shift.is_changed()
+        || best_scores.is_changed();
+
+    if !changed {
+        return;
+    }
+
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let text_style = |color: Color| TextStyle {
+        font_size: 24.0,
+        color,
+        font: fonts.default.clone(),
+    };
+
+    let title_style = |font_size| TextStyle {
+        font_size,
+        color: palette.text_title.color,
+        font: fonts.title.clone(),
+    };
+
+    let mut sections = vec![TextSection::new("Tetris\n\n", title_style(64.0))];
+
+    for (i, item) in MenuItem::all().into_iter().enumerate() {
+        let value = match item {
+            MenuItem::Mode => settings.mode.label(),
+            MenuItem::Level => settings.start_level.to_string(),
+            MenuItem::Randomizer => randomizer.kind.to_string(),
+            MenuItem::Handling => match auto_shift.profile() {
+                Some(profile) => profile.label().to_string(),
+                None => "Custom".to_string(),
+            },
+            MenuItem::Start => String::new(),
+        };
+
+        let line = match (i == menu.selected, item) {
+            (true, MenuItem::Start) => format!("\n> {} <\n", item.label()),
+            (false, MenuItem::Start) => format!("\n{}\n", item.label()),
+            (true, _) => format!("{:<12}< {value} >\n", item.label()),
+            (false, _) => format!("{:<12}  {value}  \n", item.label()),
+        };
+
+        let color = {
+            if i == menu.selected {
+                palette.text_title.color
+            } else {
+                palette.text_default.color
+            }
+        };
+
+        sections.push(TextSection::new(line, text_style(color)));
+    }
+
+    sections.push(TextSection::new("\nBest scores\n", title_style(32.0)));
+
+    for mode in GameMode::all() {
+        let best = match (best_scores.0.get(&mode), mode.line_goal()) {
+            (None, _) => "-".to_string(),
+            (Some(&score), None) => Score(score).to_string(),
+            (Some(&millis), Some(_)) => Stopwatch {
+                since_begining: Duration::from_millis(millis),
+                precise: true,
+                limit: None,
+            }
+            .to_string(),
+        };
+
+        sections.push(TextSection::new(
+            format!("{:<12}{best}\n", mode.label()),
+            text_style(palette.text_default.color),
+        ));
+    }
+
+    commands
+        .spawn((
+            Name::new("Main Menu Overlay"),
+            MainMenuOverlay,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: palette.background_2.color.into(),
+                z_index: ZIndex::Global(50),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections(sections));
+        });
+}
+
+pub(crate) fn despawn_main_menu(
+    mut commands: Commands,
+    overlays: Query<Entity, With<MainMenuOverlay>>,
+) {
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn record_best_score(
+    mut game_over: EventReader<GameOver>,
+    settings: Res<GameSettings>,
+    mut best_scores: ResMut<BestScores>,
+) {
+    for game_over in game_over.read() {
+        let score = match (settings.mode.line_goal(), game_over.cause) {
+            (None, _) => game_over.score.0,
+            (Some(_), GameEnd::GoalReached) => {
+                game_over.stopwatch.since_begining.as_millis() as u64
+            }
+            // Unfinished sprints are not ranked
+            (Some(_), GameEnd::TopOut(_) | GameEnd::TimeUp) => continue,
+        };
+
+        if best_scores.register(settings.mode, score) {
+            best_scores.save();
+        }
+    }
+}
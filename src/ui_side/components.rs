@@ -2,10 +2,31 @@ use std::marker::PhantomData;
 
 use bevy::prelude::*;
 
-#[derive(Component, Default)]
+#[derive(Component)]
 pub(crate) struct ResourceDisplay<R: Resource + std::fmt::Display> {
     _phantom: PhantomData<&'static R>,
 }
 
+// Derive would require `R: Default`
+impl<R: Resource + std::fmt::Display> Default for ResourceDisplay<R> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Preview of an upcoming piece, holding its index in the queue.
+#[derive(Component)]
+pub(crate) struct NextPiece(pub(crate) usize);
+
+#[derive(Component)]
+pub(crate) struct HoldPiece;
+
+/// Title above the level, which is replaced by remaining lines for modes
+/// with a line goal.
+#[derive(Component)]
+pub(crate) struct LevelLabel;
+
 #[derive(Component)]
-pub(crate) struct NextPiece;
+pub(crate) struct LevelDisplay;
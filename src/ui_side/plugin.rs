@@ -1,9 +1,7 @@
 use bevy::prelude::*;
 
-use crate::game_rules::plugin::GameUpdateSystems;
 use crate::game_rules::resources::Score;
 use crate::game_rules::resources::Stopwatch;
-use crate::game_rules::resources::XP;
 
 use super::resources::*;
 use super::systems::*;
@@ -11,13 +9,22 @@ use super::systems::*;
 pub(crate) struct UiSidePlugin {
     pub(crate) pos: [f32; 2],
     pub(crate) size: [f32; 2],
+    /// Number of upcoming pieces displayed, from 1 to 6
+    pub(crate) previews: u8,
 }
 
 impl Plugin for UiSidePlugin {
     fn build(&self, app: &mut App) {
+        assert!(
+            (1..=UI_SIDE_MAX_PREVIEWS).contains(&self.previews),
+            "Invalid number of previews: {}",
+            self.previews,
+        );
+
         app.insert_resource(UiSideConfig {
             pos: self.pos,
             size: self.size,
+            previews: self.previews,
         });
 
         app.init_resource::<UiSideRoot>()
@@ -31,12 +38,12 @@ impl Plugin for UiSidePlugin {
                 Update,
                 (
                     update_resource_display::<Score>,
-                    update_resource_display::<XP>,
+                    update_level_display,
                     update_resource_display::<Stopwatch>,
                     update_next_piece,
+                    update_hold_piece,
                 )
-                    .chain()
-                    .after(GameUpdateSystems),
+                    .chain(),
             );
     }
 }
@@ -6,12 +6,16 @@ pub(crate) const UI_SIDE_VIRTUAL_WIDTH: f32 = 200.0;
 pub(crate) const UI_SIDE_VIRTUAL_HEIGHT: f32 = 800.0;
 pub(crate) const UI_SIDE_BORDER: f32 = 20.0;
 
+/// Bounds for the number of upcoming pieces displayed.
+pub(crate) const UI_SIDE_MAX_PREVIEWS: u8 = 6;
+
 // -- Config
 
 #[derive(Resource)]
 pub(crate) struct UiSideConfig {
     pub(crate) pos: [f32; 2],
     pub(crate) size: [f32; 2],
+    pub(crate) previews: u8,
 }
 
 // -- Root
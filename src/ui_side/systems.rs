@@ -3,6 +3,7 @@ use bevy::sprite::Anchor;
 use bevy::sprite::Mesh2dHandle;
 
 use crate::common::resources::ColorPalette;
+use crate::game_rules::resources::HeldPiece;
 use crate::game_rules::resources::PieceGenerator;
 use crate::game_rules::resources::Score;
 use crate::game_rules::resources::Stopwatch;
@@ -33,18 +34,55 @@ pub(crate) fn setup_background(
 
 pub(crate) fn setup_preview(
     mut commands: Commands,
+    config: Res<UiSideConfig>,
     meshes: Res<MeshCollection>,
     palette: Res<ColorPalette>,
     root: Res<UiSideRoot>,
 ) {
-    let preview = commands
+    for index in 0..usize::from(config.previews) {
+        // First piece is displayed bigger, others are stacked below
+        let (y, scale) = {
+            if index == 0 {
+                (170.0, 0.6)
+            } else {
+                (115.0 - 45.0 * index as f32, 0.2)
+            }
+        };
+
+        let preview = commands
+            .spawn((
+                Name::new(format!("Next Piece Frame #{index}")),
+                ColorMesh2dBundle {
+                    mesh: meshes.preview_box.clone().into(),
+                    material: palette.background_1.material.clone(),
+                    transform: Transform::from_translation([0.0, y, -100.0].into())
+                        .with_scale(Vec3::new(scale, scale, 1.0)),
+                    ..Default::default()
+                },
+            ))
+            .set_parent(**root)
+            .id();
+
+        commands
+            .spawn((
+                Name::new(format!("Next Piece #{index}")),
+                NextPiece(index),
+                ColorMesh2dBundle {
+                    transform: Transform::from_translation([0.0, 0.0, 50.0].into()),
+                    ..Default::default()
+                },
+            ))
+            .set_parent(preview);
+    }
+
+    let hold = commands
         .spawn((
-            Name::new("Next Piece Frame"),
+            Name::new("Hold Piece Frame"),
             ColorMesh2dBundle {
                 mesh: meshes.preview_box.clone().into(),
                 material: palette.background_1.material.clone(),
-                transform: Transform::from_translation([0.0, 0.0, -100.0].into())
-                    .with_scale(Vec3::new(0.8, 0.8, 1.0)),
+                transform: Transform::from_translation([0.0, 300.0, -100.0].into())
+                    .with_scale(Vec3::new(0.5, 0.5, 1.0)),
                 ..Default::default()
             },
         ))
@@ -53,14 +91,14 @@ pub(crate) fn setup_preview(
 
     commands
         .spawn((
-            Name::new("Next Piece"),
-            NextPiece,
+            Name::new("Hold Piece"),
+            HoldPiece,
             ColorMesh2dBundle {
                 transform: Transform::from_translation([0.0, 0.0, 50.0].into()),
                 ..Default::default()
             },
         ))
-        .set_parent(preview);
+        .set_parent(hold);
 }
 
 pub(crate) fn setup_score_pannel(
@@ -116,6 +154,7 @@ pub(crate) fn setup_score_pannel(
     commands
         .spawn((
             Name::new("Level Label"),
+            LevelLabel,
             Text2dBundle {
                 text: Text::from_section(
                     "Level",
@@ -138,7 +177,7 @@ pub(crate) fn setup_score_pannel(
     commands
         .spawn((
             Name::new("Level Display"),
-            ResourceDisplay::<XP>::default(),
+            LevelDisplay,
             Text2dBundle {
                 text: Text::from_section(
                     "1",
@@ -215,17 +254,78 @@ pub(crate) fn update_resource_display<R: Resource + std::fmt::Display>(
     }
 }
 
+pub(crate) fn update_level_display(
+    mut labels: Query<Mut<Text>, (With<LevelLabel>, Without<LevelDisplay>)>,
+    mut displays: Query<Mut<Text>, With<LevelDisplay>>,
+    xp: Res<XP>,
+) {
+    if !xp.is_changed() {
+        return;
+    }
+
+    let (label, value) = match xp.lines_remaining() {
+        Some(remaining) => ("Lines", remaining.to_string()),
+        None => ("Level", xp.to_string()),
+    };
+
+    for mut text in &mut labels {
+        text.sections[0].value = label.to_string();
+    }
+
+    for mut text in &mut displays {
+        text.sections[0].value = value.clone();
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub(crate) fn update_next_piece(
     mut rng: ResMut<PieceGenerator>,
-    mut previews: Query<(Mut<Mesh2dHandle>, Mut<Handle<ColorMaterial>>), With<NextPiece>>,
+    mut previews: Query<(&NextPiece, Mut<Mesh2dHandle>, Mut<Handle<ColorMaterial>>)>,
+    config: Res<UiSideConfig>,
     grid_meshes: Res<GridMeshCollection>,
     palette: Res<ColorPalette>,
 ) {
-    let next_piece = rng.peek();
+    let next_pieces: Vec<_> = rng.peek_n(config.previews.into()).collect();
+
+    for (&NextPiece(index), mut mesh, mut material) in &mut previews {
+        let kind = next_pieces[index];
+        *mesh = grid_meshes.pieces_preview_blocks[kind].clone().into();
+        *material = palette.pieces[kind].material.clone();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_hold_piece(
+    held: Res<HeldPiece>,
+    mut previews: Query<
+        (
+            Mut<Mesh2dHandle>,
+            Mut<Handle<ColorMaterial>>,
+            Mut<Visibility>,
+        ),
+        With<HoldPiece>,
+    >,
+    grid_meshes: Res<GridMeshCollection>,
+    palette: Res<ColorPalette>,
+) {
+    if !held.is_changed() {
+        return;
+    }
+
+    for (mut mesh, mut material, mut visibility) in &mut previews {
+        let Some(kind) = held.kind else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Inherited;
+        *mesh = grid_meshes.pieces_preview_blocks[kind].clone().into();
 
-    for (mut mesh, mut material) in &mut previews {
-        *mesh = grid_meshes.pieces_small_blocks[next_piece].clone().into();
-        *material = palette.pieces[next_piece].material.clone();
+        // Hold piece is greyed out until it can be swapped again
+        *material = if held.locked {
+            palette.ghosts[kind].material.clone()
+        } else {
+            palette.pieces[kind].material.clone()
+        };
     }
 }
@@ -0,0 +1,132 @@
+//! Compare queries of the bitboard with a cell by cell implementation.
+//!
+//! Run with `cargo bench -p tetris-core`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tetris_core::board::{Board, BoardConfig};
+use tetris_core::piece::{GridPos, PieceKind, Spin};
+
+/// Filled cells stored in a grid of cells, as the board used to do.
+#[derive(Clone)]
+struct CellBoard {
+    config: BoardConfig,
+    cells: [[Option<PieceKind>; 22]; 10],
+}
+
+impl CellBoard {
+    fn new(board: &Board) -> Self {
+        let mut cells = [[None; 22]; 10];
+
+        for (x, column) in (0..).zip(&mut cells) {
+            for (y, cell) in (0..).zip(column) {
+                *cell = board.get(&GridPos { x, y });
+            }
+        }
+
+        Self {
+            config: *board.config(),
+            cells,
+        }
+    }
+
+    fn is_filled(&self, pos: &GridPos) -> bool {
+        self.cells
+            .get(usize::from(pos.x))
+            .and_then(|column| column.get(usize::from(pos.y)))
+            .is_some_and(Option::is_some)
+    }
+
+    fn conflicts(&self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
+        !kind
+            .piece_covered_cells(pos, spin)
+            .all(|pos| self.config.contains(&pos) && !self.is_filled(&pos))
+    }
+
+    fn completed_rows(&self) -> Vec<u8> {
+        (0..self.config.height)
+            .filter(|&y| (0..self.config.width).all(|x| self.is_filled(&GridPos { x, y })))
+            .collect()
+    }
+}
+
+/// A messy stack, with a few completed rows.
+fn sample_board() -> Board {
+    let mut board = Board::default();
+    let config = *board.config();
+
+    for y in 0..12 {
+        for x in 0..config.width {
+            if y % 4 == 0 || (x * 7 + y * 3) % 5 != 0 {
+                board.set(&GridPos { x, y }, Some(PieceKind::T));
+            }
+        }
+    }
+
+    board
+}
+
+/// All placements of all pieces, which a bot would check when searching
+/// for moves.
+fn placements(config: &BoardConfig) -> Vec<(PieceKind, GridPos, Spin)> {
+    let mut res = Vec::new();
+
+    for kind in PieceKind::all() {
+        for spin in (0..4).map(Spin) {
+            for x in 0..config.width {
+                for y in 0..config.height {
+                    res.push((kind, GridPos { x, y }, spin));
+                }
+            }
+        }
+    }
+
+    res
+}
+
+fn bench_board(c: &mut Criterion) {
+    let board = sample_board();
+    let cell_board = CellBoard::new(&board);
+    let placements = placements(board.config());
+
+    let mut group = c.benchmark_group("conflicts");
+
+    group.bench_function("bitboard", |b| {
+        b.iter(|| {
+            placements
+                .iter()
+                .filter(|&&(kind, pos, spin)| board.conflicts(kind, pos, spin))
+                .count()
+        })
+    });
+
+    group.bench_function("cells", |b| {
+        b.iter(|| {
+            placements
+                .iter()
+                .filter(|&&(kind, pos, spin)| cell_board.conflicts(kind, pos, spin))
+                .count()
+        })
+    });
+
+    group.finish();
+
+    let mut group = c.benchmark_group("completed_rows");
+    group.bench_function("bitboard", |b| {
+        b.iter(|| black_box(&board).completed_rows())
+    });
+    group.bench_function("cells", |b| {
+        b.iter(|| black_box(&cell_board).completed_rows())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_function("bitboard", |b| b.iter(|| black_box(board.bits()).clone()));
+    group.bench_function("board", |b| b.iter(|| black_box(&board).clone()));
+    group.bench_function("cells", |b| b.iter(|| black_box(&cell_board).clone()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_board);
+criterion_main!(benches);
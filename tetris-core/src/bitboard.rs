@@ -0,0 +1,194 @@
+//! Compact representation of filled cells, with one bitmask per row.
+//!
+//! Bit `x` of a row is set when the cell in column `x` is filled, which turns
+//! collision checks and line detection into a few integer operations.
+
+use crate::piece::{GridPos, PieceKind, Spin};
+
+// -- PieceMask
+
+/// Rows covered by a piece in a given spin, as bitmasks relative to the
+/// bottom-left corner of its bounding box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PieceMask {
+    /// Position of the bottom-left corner relative to the position of the piece
+    pub offset: [i8; 2],
+    /// Bounding box of the piece, as a number of columns and rows
+    pub size: [u8; 2],
+    /// Cells covered by the piece, from its lowest row
+    pub rows: [u32; 4],
+}
+
+impl PieceMask {
+    pub const fn new(kind: PieceKind, spin: Spin) -> Self {
+        let cells = kind.rotation(spin);
+        let mut min = cells[0];
+        let mut max = cells[0];
+        let mut i = 1;
+
+        while i < 4 {
+            let [x, y] = cells[i];
+            min = [
+                if x < min[0] { x } else { min[0] },
+                if y < min[1] { y } else { min[1] },
+            ];
+            max = [
+                if x > max[0] { x } else { max[0] },
+                if y > max[1] { y } else { max[1] },
+            ];
+            i += 1;
+        }
+
+        let mut rows = [0; 4];
+        i = 0;
+
+        while i < 4 {
+            let [x, y] = cells[i];
+            rows[(y - min[1]) as usize] |= 1 << (x - min[0]);
+            i += 1;
+        }
+
+        Self {
+            offset: min,
+            size: [(max[0] - min[0] + 1) as u8, (max[1] - min[1] + 1) as u8],
+            rows,
+        }
+    }
+
+    /// Precomputed mask of a piece.
+    pub fn get(kind: PieceKind, spin: Spin) -> &'static Self {
+        &PIECE_MASKS[kind as usize][usize::from(spin.0 % 4)]
+    }
+}
+
+const PIECE_MASKS: [[PieceMask; 4]; 7] = {
+    let kinds = PieceKind::all();
+    let mut masks = [[PieceMask::new(PieceKind::I, Spin(0)); 4]; 7];
+    let mut k = 0;
+
+    while k < kinds.len() {
+        let mut spin = 0;
+
+        while spin < 4 {
+            masks[kinds[k] as usize][spin as usize] = PieceMask::new(kinds[k], Spin(spin));
+            spin += 1;
+        }
+
+        k += 1;
+    }
+
+    masks
+};
+
+// -- BitBoard
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitBoard {
+    width: u8,
+    rows: Vec<u32>,
+}
+
+impl BitBoard {
+    /// Widest board that fits in the bitmask of a row.
+    pub const MAX_WIDTH: u8 = u32::BITS as u8;
+
+    /// An empty board, panics if it is wider than `MAX_WIDTH`.
+    pub fn new(width: u8, height: u8) -> Self {
+        assert!(width <= Self::MAX_WIDTH, "Board is too wide: {width}");
+
+        Self {
+            width,
+            rows: vec![0; usize::from(height)],
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.rows.len() as u8
+    }
+
+    /// Mask of a row where all cells are filled.
+    pub fn full_row(&self) -> u32 {
+        u32::MAX
+            .checked_shr(u32::from(Self::MAX_WIDTH - self.width))
+            .unwrap_or(0)
+    }
+
+    /// Filled cells of a row, rows outside of the board are empty.
+    pub fn row(&self, y: u8) -> u32 {
+        self.rows.get(usize::from(y)).copied().unwrap_or(0)
+    }
+
+    pub fn is_filled(&self, pos: &GridPos) -> bool {
+        pos.x < self.width && self.row(pos.y) & (1 << pos.x) != 0
+    }
+
+    /// Fill or empty a cell, panics if the position is outside of the grid.
+    pub fn set(&mut self, pos: &GridPos, filled: bool) {
+        assert!(pos.x < self.width, "Position out of the grid: {pos}");
+        let row = &mut self.rows[usize::from(pos.y)];
+
+        if filled {
+            *row |= 1 << pos.x;
+        } else {
+            *row &= !(1 << pos.x);
+        }
+    }
+
+    pub fn is_row_full(&self, y: u8) -> bool {
+        self.row(y) == self.full_row()
+    }
+
+    pub fn is_row_empty(&self, y: u8) -> bool {
+        self.row(y) == 0
+    }
+
+    /// Check if the piece overlaps with filled cells or with the edges of the
+    /// board.
+    pub fn conflicts(&self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
+        let mask = PieceMask::get(kind, spin);
+        let x = i16::from(pos.x) + i16::from(mask.offset[0]);
+        let y = i16::from(pos.y) + i16::from(mask.offset[1]);
+
+        if x < 0
+            || y < 0
+            || x + i16::from(mask.size[0]) > i16::from(self.width)
+            || y + i16::from(mask.size[1]) > self.rows.len() as i16
+        {
+            return true;
+        }
+
+        mask.rows[..usize::from(mask.size[1])]
+            .iter()
+            .zip(&self.rows[y as usize..])
+            .any(|(piece_row, row)| (piece_row << x) & row != 0)
+    }
+
+    /// Indices of the rows that are completely filled.
+    pub fn completed_rows(&self) -> Vec<u8> {
+        let full_row = self.full_row();
+
+        (0..=u8::MAX)
+            .zip(&self.rows)
+            .filter(|(_, &row)| row == full_row)
+            .map(|(y, _)| y)
+            .collect()
+    }
+
+    /// Remove given rows and shift the rows above them down.
+    pub fn clear_rows(&mut self, rows: &[u8]) {
+        let mut target_line = 0;
+
+        for (y, i) in (0..=u8::MAX).zip(0..self.rows.len()) {
+            if !rows.contains(&y) {
+                self.rows[target_line] = self.rows[i];
+                target_line += 1;
+            }
+        }
+
+        self.rows[target_line..].fill(0);
+    }
+}
@@ -0,0 +1,356 @@
+//! Grid of locked cells and collisions of pieces with it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bitboard::BitBoard;
+use crate::piece::{GridPos, PieceKind, Spin};
+use crate::rotation::{Kicks180, Rotation};
+
+pub const GRID_WIDTH: u8 = 10;
+pub const GRID_HEIGHT: u8 = 22;
+pub const GRID_VISIBLE_HEIGHT: u8 = 20;
+
+/// Rows of the buffer zone above the skyline, where pieces spawn.
+pub const GRID_BUFFER_HEIGHT: u8 = GRID_HEIGHT - GRID_VISIBLE_HEIGHT;
+
+// -- BoardConfig
+
+/// Dimensions of the board, the guideline's board is 10 cells wide with 20
+/// visible rows.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoardConfig {
+    pub width: u8,
+    /// Total number of rows, including the buffer zone
+    pub height: u8,
+    pub visible_height: u8,
+}
+
+impl BoardConfig {
+    /// Narrowest board where every piece can spawn and rotate.
+    pub const MIN_WIDTH: u8 = 4;
+
+    /// Widest board where rows fit in a bitmask.
+    pub const MAX_WIDTH: u8 = BitBoard::MAX_WIDTH;
+
+    pub const GUIDELINE: Self = Self {
+        width: GRID_WIDTH,
+        height: GRID_HEIGHT,
+        visible_height: GRID_VISIBLE_HEIGHT,
+    };
+
+    /// A board with given visible dimensions and a guideline buffer zone.
+    pub fn new(width: u8, visible_height: u8) -> Result<Self, String> {
+        if !(Self::MIN_WIDTH..=Self::MAX_WIDTH).contains(&width) {
+            return Err(format!(
+                "board must be {} to {} cells wide",
+                Self::MIN_WIDTH,
+                Self::MAX_WIDTH,
+            ));
+        }
+
+        let height = visible_height
+            .checked_add(GRID_BUFFER_HEIGHT)
+            .filter(|_| visible_height >= 1)
+            .ok_or(format!("invalid board height `{visible_height}`"))?;
+
+        Ok(Self {
+            width,
+            height,
+            visible_height,
+        })
+    }
+
+    /// Check if a position is inside of the grid.
+    pub fn contains(&self, pos: &GridPos) -> bool {
+        (0..self.width).contains(&pos.x) && (0..self.height).contains(&pos.y)
+    }
+
+    /// Position of a new piece at the top of the grid, its lowest cells are
+    /// in the first row of the buffer zone above the skyline.
+    pub fn spawn_position(&self, kind: PieceKind) -> GridPos {
+        // Pieces are centered, rounding to the left when that is not exact
+        let x = if kind.base_width() % 2 == 0 {
+            self.width / 2
+        } else {
+            (self.width - 1) / 2
+        };
+
+        let y = self.visible_height.wrapping_add_signed(
+            -kind
+                .base_shape()
+                .into_iter()
+                .map(|[_, y]| y)
+                .min()
+                .unwrap_or(0),
+        );
+
+        GridPos { x, y }
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self::GUIDELINE
+    }
+}
+
+impl fmt::Display for BoardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.visible_height)
+    }
+}
+
+impl FromStr for BoardConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid board dimensions `{s}`, expected <width>x<height>");
+        let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+        let width = width.parse().map_err(|_| invalid())?;
+        let height = height.parse().map_err(|_| invalid())?;
+        Self::new(width, height)
+    }
+}
+
+// -- TSpin
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TSpin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+// -- Board
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    config: BoardConfig,
+    /// Filled cells, which answer all collision queries
+    bits: BitBoard,
+    /// Kinds of the pieces that filled each cell, stored row by row
+    kinds: Vec<Option<PieceKind>>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new(BoardConfig::default())
+    }
+}
+
+impl Board {
+    pub fn new(config: BoardConfig) -> Self {
+        Self {
+            config,
+            bits: BitBoard::new(config.width, config.height),
+            kinds: vec![None; usize::from(config.width) * usize::from(config.height)],
+        }
+    }
+
+    pub fn config(&self) -> &BoardConfig {
+        &self.config
+    }
+
+    /// Filled cells as bitmasks, which are cheap to clone when searching for
+    /// moves.
+    pub fn bits(&self) -> &BitBoard {
+        &self.bits
+    }
+
+    fn index(&self, pos: &GridPos) -> Option<usize> {
+        self.config
+            .contains(pos)
+            .then(|| usize::from(pos.y) * usize::from(self.config.width) + usize::from(pos.x))
+    }
+
+    /// Position of a new piece, which immediately drops by one row if
+    /// possible, or `None` if it overlaps with filled cells.
+    /// See https://tetris.wiki/Tetris_Guideline
+    pub fn spawn(&self, kind: PieceKind, spin: Spin) -> Option<GridPos> {
+        let mut pos = self.config.spawn_position(kind);
+
+        if self.conflicts(kind, pos, spin) {
+            return None;
+        }
+
+        self.try_move([0, -1], kind, &mut pos, spin);
+        Some(pos)
+    }
+
+    /// Kind of the piece that filled given cell, if any.
+    pub fn get(&self, pos: &GridPos) -> Option<PieceKind> {
+        self.kinds[self.index(pos)?]
+    }
+
+    /// Fill or empty a cell, panics if the position is outside of the grid.
+    pub fn set(&mut self, pos: &GridPos, cell: Option<PieceKind>) {
+        let index = self.index(pos).expect("Position out of the grid");
+        self.kinds[index] = cell;
+        self.bits.set(pos, cell.is_some());
+    }
+
+    pub fn is_empty(&self, pos: &GridPos) -> bool {
+        self.config.contains(pos) && !self.is_filled(pos)
+    }
+
+    pub fn is_filled(&self, pos: &GridPos) -> bool {
+        self.bits.is_filled(pos)
+    }
+
+    pub fn is_row_full(&self, y: u8) -> bool {
+        self.bits.is_row_full(y)
+    }
+
+    pub fn is_row_empty(&self, y: u8) -> bool {
+        self.bits.is_row_empty(y)
+    }
+
+    /// Check if the grid will be empty once completed rows are cleared.
+    pub fn is_perfect_clear(&self) -> bool {
+        (0..self.config.height).all(|y| self.is_row_full(y) || self.is_row_empty(y))
+    }
+
+    pub fn conflicts(&self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
+        self.bits.conflicts(kind, pos, spin)
+    }
+
+    pub fn try_move(&self, delta: [i8; 2], kind: PieceKind, pos: &mut GridPos, spin: Spin) -> bool {
+        let new_pos = GridPos {
+            x: pos.x.wrapping_add_signed(delta[0]),
+            y: pos.y.wrapping_add_signed(delta[1]),
+        };
+
+        if self.conflicts(kind, new_pos, spin) {
+            return false;
+        }
+
+        *pos = new_pos;
+        true
+    }
+
+    /// Rotate the piece, trying each kick of the rotation system until the
+    /// piece fits. Returns the index of the kick that was used.
+    pub fn try_rotate(
+        &self,
+        kind: PieceKind,
+        pos: &mut GridPos,
+        spin: &mut Spin,
+        rotation: Rotation,
+        kicks_180: Kicks180,
+    ) -> Option<usize> {
+        let new_spin = rotation.apply(*spin);
+
+        let kick = kind
+            .kicks(*spin, rotation, kicks_180)
+            .iter()
+            .position(|&dir| self.try_move(dir, kind, pos, new_spin))?;
+
+        *spin = new_spin;
+        Some(kick)
+    }
+
+    /// Classify a T piece about to lock using the 3-corner rule,
+    /// `last_rotation` is the last rotation and the index of its kick if it
+    /// was the last successful move of the piece.
+    /// See https://tetris.wiki/T-Spin
+    pub fn t_spin(
+        &self,
+        kind: PieceKind,
+        pos: GridPos,
+        spin: Spin,
+        last_rotation: Option<(Rotation, usize)>,
+    ) -> TSpin {
+        let Some((rotation, kick)) = last_rotation else {
+            return TSpin::None;
+        };
+
+        if kind != PieceKind::T {
+            return TSpin::None;
+        }
+
+        let is_blocked = |[x, y]: [i8; 2]| {
+            !self.is_empty(&GridPos {
+                x: pos.x.wrapping_add_signed(x),
+                y: pos.y.wrapping_add_signed(y),
+            })
+        };
+
+        let corners = [[-1, 1], [1, 1], [1, -1], [-1, -1]];
+        let blocked_corners = corners.into_iter().filter(|&c| is_blocked(c)).count();
+
+        if blocked_corners < 3 {
+            return TSpin::None;
+        }
+
+        // Corners on each side of the pointing cell, in clockwise order
+        let front = usize::from(spin.0 % 4);
+        let front_blocked = is_blocked(corners[front]) && is_blocked(corners[(front + 1) % 4]);
+
+        // The last kick of SRS is only reachable with a T-spin triple-like
+        // setup, which is always rewarded as a full T-spin. This doesn't apply
+        // to 180° rotations, which use their own table.
+        let triple_kick = rotation != Rotation::Half && kick == 4;
+
+        if front_blocked || triple_kick {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
+    }
+
+    /// Move the piece down as far as possible, returns the number of rows it
+    /// went through.
+    pub fn drop(&self, kind: PieceKind, pos: &mut GridPos, spin: Spin) -> u8 {
+        let mut rows = 0;
+
+        while self.try_move([0, -1], kind, pos, spin) {
+            rows += 1;
+        }
+
+        rows
+    }
+
+    /// Fill the cells covered by a piece, returns `true` if the piece was
+    /// locked entirely above the visible area (lock out).
+    pub fn lock(&mut self, kind: PieceKind, pos: GridPos, spin: Spin) -> bool {
+        let mut above_skyline = true;
+
+        for cell in kind.piece_covered_cells(pos, spin) {
+            assert!(self.is_empty(&cell), "Locking on a filled cell: {cell}");
+            above_skyline &= cell.y >= self.config.visible_height;
+            self.set(&cell, Some(kind));
+        }
+
+        above_skyline
+    }
+
+    /// Indices of the rows that are completely filled, including rows of the
+    /// buffer zone.
+    pub fn completed_rows(&self) -> Vec<u8> {
+        self.bits.completed_rows()
+    }
+
+    /// Remove given rows and shift the rows above them down.
+    pub fn clear_rows(&mut self, rows: &[u8]) {
+        let mut target_line = 0;
+
+        let width = usize::from(self.config.width);
+        self.bits.clear_rows(rows);
+
+        for y in 0..self.config.height {
+            if rows.contains(&y) {
+                continue;
+            }
+
+            let start = usize::from(y) * width;
+            self.kinds
+                .copy_within(start..start + width, target_line * width);
+            target_line += 1;
+        }
+
+        self.kinds[target_line * width..].fill(None);
+    }
+}
@@ -0,0 +1,334 @@
+//! Automatic player, which searches every reachable placement of a piece and
+//! picks the one leaving the best looking board.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bitboard::BitBoard;
+use crate::board::Board;
+use crate::input::PlayerInput;
+use crate::piece::{GridPos, PieceKind, Spin};
+use crate::rotation::{Kicks180, Rotation};
+
+/// Inputs explored from each position of the piece, a soft drop stands for a
+/// single row down.
+const SEARCH_INPUTS: [PlayerInput; 6] = [
+    PlayerInput::MoveLeft,
+    PlayerInput::MoveRight,
+    PlayerInput::SoftDrop,
+    PlayerInput::RotateRight,
+    PlayerInput::RotateLeft,
+    PlayerInput::Rotate180,
+];
+
+// -- Features
+
+/// Measures of a board after a piece is locked and lines are cleared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Features {
+    /// Empty cells with a filled cell above them
+    pub holes: u32,
+    /// Sum of height differences between adjacent columns
+    pub bumpiness: u32,
+    /// Sum of the heights of all columns
+    pub aggregate_height: u32,
+    /// Sum of the depths of columns lower than both of their neighbours,
+    /// walls count as infinitely high
+    pub well_depth: u32,
+    /// Lines cleared by the placement
+    pub lines: u32,
+}
+
+impl Features {
+    pub fn new(bits: &BitBoard, lines: u32) -> Self {
+        let heights: Vec<u32> = (0..bits.width())
+            .map(|x| {
+                (0..bits.height())
+                    .rev()
+                    .find(|&y| bits.is_filled(&GridPos { x, y }))
+                    .map_or(0, |y| u32::from(y) + 1)
+            })
+            .collect();
+
+        let holes = (0..bits.width())
+            .zip(&heights)
+            .map(|(x, &height)| {
+                (0..height)
+                    .filter(|&y| !bits.is_filled(&GridPos { x, y: y as u8 }))
+                    .count() as u32
+            })
+            .sum();
+
+        let bumpiness = heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+
+        let well_depth = (0..heights.len())
+            .map(|x| {
+                let left = x.checked_sub(1).map_or(u32::MAX, |x| heights[x]);
+                let right = heights.get(x + 1).copied().unwrap_or(u32::MAX);
+                left.min(right).saturating_sub(heights[x])
+            })
+            .sum();
+
+        Self {
+            holes,
+            bumpiness,
+            aggregate_height: heights.iter().sum(),
+            well_depth,
+            lines,
+        }
+    }
+}
+
+// -- Weights
+
+/// Weight of each feature in the evaluation of a board, higher scores are
+/// better.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    pub holes: f32,
+    pub bumpiness: f32,
+    pub aggregate_height: f32,
+    pub well_depth: f32,
+    pub lines: f32,
+}
+
+impl Default for Weights {
+    /// Weights tuned by a genetic algorithm for a bot that doesn't look
+    /// ahead.
+    /// See https://codemyroad.wordpress.com/2013/04/14/tetris-ai-the-near-perfect-player/
+    fn default() -> Self {
+        Self {
+            holes: -0.36,
+            bumpiness: -0.18,
+            aggregate_height: -0.51,
+            well_depth: -0.1,
+            lines: 0.76,
+        }
+    }
+}
+
+impl Weights {
+    /// Names of the weights, as used by their text representation.
+    pub const NAMES: [&'static str; 5] = ["holes", "bumpiness", "height", "wells", "lines"];
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "holes" => Some(&mut self.holes),
+            "bumpiness" => Some(&mut self.bumpiness),
+            "height" => Some(&mut self.aggregate_height),
+            "wells" => Some(&mut self.well_depth),
+            "lines" => Some(&mut self.lines),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate(&self, features: &Features) -> f32 {
+        self.holes * features.holes as f32
+            + self.bumpiness * features.bumpiness as f32
+            + self.aggregate_height * features.aggregate_height as f32
+            + self.well_depth * features.well_depth as f32
+            + self.lines * features.lines as f32
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            holes,
+            bumpiness,
+            aggregate_height,
+            well_depth,
+            lines,
+        } = self;
+
+        write!(
+            f,
+            "holes={holes},bumpiness={bumpiness},height={aggregate_height},\
+             wells={well_depth},lines={lines}"
+        )
+    }
+}
+
+/// Comma separated list of `<name>=<weight>`, missing weights keep their
+/// default value.
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::default();
+
+        for item in s.split(',') {
+            let invalid = || format!("invalid weight `{item}`");
+            let (name, value) = item.split_once('=').ok_or_else(invalid)?;
+
+            *res.get_mut(name).ok_or_else(|| {
+                format!("unknown weight `{name}`, expected one of {:?}", Self::NAMES)
+            })? = value.parse().map_err(|_| invalid())?;
+        }
+
+        Ok(res)
+    }
+}
+
+// -- Placement
+
+/// Final position of a piece, along with the inputs that bring it there from
+/// its current position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub pos: GridPos,
+    pub spin: Spin,
+    /// Inputs to apply, ending with a hard drop. A soft drop is followed by
+    /// its release as soon as the piece moved a row down.
+    pub inputs: Vec<PlayerInput>,
+}
+
+impl Placement {
+    /// Cells of the board once the piece is locked and lines are cleared.
+    pub fn apply(&self, board: &Board, kind: PieceKind) -> (BitBoard, Features) {
+        let mut bits = board.bits().clone();
+
+        for cell in kind.piece_covered_cells(self.pos, self.spin) {
+            bits.set(&cell, true);
+        }
+
+        let rows = bits.completed_rows();
+        bits.clear_rows(&rows);
+        let features = Features::new(&bits, rows.len() as u32);
+        (bits, features)
+    }
+}
+
+/// Every placement of a piece that can be reached with moves, rotations and
+/// soft drops followed by a hard drop, using the shortest sequence of inputs.
+/// This includes tucks and spins under overhangs.
+pub fn placements(
+    board: &Board,
+    kind: PieceKind,
+    pos: GridPos,
+    spin: Spin,
+    kicks_180: Kicks180,
+) -> Vec<Placement> {
+    let mut res = Vec::new();
+    let mut visited = HashSet::from([(pos, spin)]);
+    let mut landed = HashSet::new();
+    let mut queue = VecDeque::from([(pos, spin, Vec::new())]);
+
+    while let Some((pos, spin, inputs)) = queue.pop_front() {
+        let mut landing = pos;
+        board.drop(kind, &mut landing, spin);
+
+        // Symmetric pieces cover the same cells with different spins
+        let mut cells: Vec<_> = kind
+            .piece_covered_cells(landing, spin)
+            .map(|cell| (cell.x, cell.y))
+            .collect();
+
+        cells.sort_unstable();
+
+        if landed.insert(cells) {
+            let mut inputs = inputs.clone();
+            inputs.push(PlayerInput::HardDrop);
+
+            res.push(Placement {
+                pos: landing,
+                spin,
+                inputs,
+            });
+        }
+
+        for input in SEARCH_INPUTS {
+            let (mut next_pos, mut next_spin) = (pos, spin);
+
+            let moved = match input {
+                PlayerInput::MoveLeft => board.try_move([-1, 0], kind, &mut next_pos, spin),
+                PlayerInput::MoveRight => board.try_move([1, 0], kind, &mut next_pos, spin),
+                PlayerInput::SoftDrop => board.try_move([0, -1], kind, &mut next_pos, spin),
+                _ => {
+                    let rotation = match input {
+                        PlayerInput::RotateRight => Rotation::Clockwise,
+                        PlayerInput::RotateLeft => Rotation::CounterClockwise,
+                        _ => Rotation::Half,
+                    };
+
+                    board
+                        .try_rotate(kind, &mut next_pos, &mut next_spin, rotation, kicks_180)
+                        .is_some()
+                }
+            };
+
+            if moved && visited.insert((next_pos, next_spin)) {
+                let mut inputs = inputs.clone();
+                inputs.push(input);
+
+                if input == PlayerInput::SoftDrop {
+                    inputs.push(PlayerInput::SoftDropRelease);
+                }
+
+                queue.push_back((next_pos, next_spin, inputs));
+            }
+        }
+    }
+
+    res
+}
+
+// -- Bot
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bot {
+    pub weights: Weights,
+}
+
+impl Bot {
+    /// Best placement of a piece with its score, if it can be placed at all.
+    pub fn best_placement(
+        &self,
+        board: &Board,
+        kind: PieceKind,
+        pos: GridPos,
+        spin: Spin,
+        kicks_180: Kicks180,
+    ) -> Option<(Placement, f32)> {
+        placements(board, kind, pos, spin, kicks_180)
+            .into_iter()
+            .map(|placement| {
+                let (_, features) = placement.apply(board, kind);
+                let score = self.weights.evaluate(&features);
+                (placement, score)
+            })
+            // Ties are broken in favor of the shortest sequence of inputs
+            .reduce(|best, other| if other.1 > best.1 { other } else { best })
+    }
+
+    /// Inputs to send for the falling piece. When `hold` is the piece that
+    /// would replace it, a single hold input is returned if that piece has a
+    /// better placement.
+    pub fn plan(
+        &self,
+        board: &Board,
+        kind: PieceKind,
+        pos: GridPos,
+        spin: Spin,
+        hold: Option<PieceKind>,
+        kicks_180: Kicks180,
+    ) -> Vec<PlayerInput> {
+        // The current position is always a valid start for the search
+        let Some((placement, score)) = self.best_placement(board, kind, pos, spin, kicks_180)
+        else {
+            return vec![PlayerInput::HardDrop];
+        };
+
+        let held = hold.and_then(|hold| {
+            let pos = board.spawn(hold, Spin(0))?;
+            self.best_placement(board, hold, pos, Spin(0), kicks_180)
+        });
+
+        if held.is_some_and(|(_, held_score)| held_score > score) {
+            vec![PlayerInput::Hold]
+        } else {
+            placement.inputs
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Actions the player can take on the falling piece.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerInput {
+    MoveLeft,
+    MoveRight,
+    HardDrop,
+    RotateRight,
+    RotateLeft,
+    Rotate180,
+    Hold,
+    /// Speed up gravity until the soft drop is released
+    SoftDrop,
+    SoftDropRelease,
+    /// Move to the wall on every tick until the shift is released
+    ShiftLeft,
+    ShiftRight,
+    ShiftRelease,
+}
+
+impl PlayerInput {
+    pub const fn all() -> [Self; 12] {
+        [
+            Self::MoveLeft,
+            Self::MoveRight,
+            Self::HardDrop,
+            Self::RotateRight,
+            Self::RotateLeft,
+            Self::Rotate180,
+            Self::Hold,
+            Self::SoftDrop,
+            Self::SoftDropRelease,
+            Self::ShiftLeft,
+            Self::ShiftRight,
+            Self::ShiftRelease,
+        ]
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "move-left",
+            Self::MoveRight => "move-right",
+            Self::HardDrop => "hard-drop",
+            Self::RotateRight => "rotate-right",
+            Self::RotateLeft => "rotate-left",
+            Self::Rotate180 => "rotate-180",
+            Self::Hold => "hold",
+            Self::SoftDrop => "soft-drop",
+            Self::SoftDropRelease => "soft-drop-release",
+            Self::ShiftLeft => "shift-left",
+            Self::ShiftRight => "shift-right",
+            Self::ShiftRelease => "shift-release",
+        }
+    }
+}
+
+impl Display for PlayerInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for PlayerInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|input| input.name() == s)
+            .ok_or_else(|| format!("unknown input `{s}`"))
+    }
+}
@@ -0,0 +1,15 @@
+//! Rules of the game, independent of any engine.
+//!
+//! Guidelines : https://harddrop.com/wiki/Tetris_Guideline
+
+pub mod bitboard;
+pub mod board;
+pub mod bot;
+pub mod input;
+pub mod piece;
+pub mod randomizer;
+pub mod rotation;
+pub mod scoring;
+
+#[cfg(test)]
+mod tests;
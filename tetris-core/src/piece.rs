@@ -0,0 +1,121 @@
+//! Shapes of the pieces and how they rotate.
+
+// -- Spin
+
+/// Rotation state of a piece, as a number of clockwise quarter turns.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Spin(pub u8);
+
+// -- PieceKind
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, enum_map::Enum)]
+pub enum PieceKind {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+}
+
+impl PieceKind {
+    pub const fn all() -> [Self; 7] {
+        [
+            Self::I,
+            Self::O,
+            Self::T,
+            Self::S,
+            Self::Z,
+            Self::J,
+            Self::L,
+        ]
+    }
+
+    pub const fn base_shape(self) -> [[i8; 2]; 4] {
+        match self {
+            PieceKind::I => [[-2, 0], [-1, 0], [0, 0], [1, 0]],
+            PieceKind::O => [[-1, -1], [0, -1], [-1, 0], [0, 0]],
+            PieceKind::T => [[-1, 0], [0, 0], [1, 0], [0, 1]],
+            PieceKind::S => [[-1, 0], [0, 0], [0, 1], [1, 1]],
+            PieceKind::Z => [[-1, 1], [0, 0], [0, 1], [1, 0]],
+            PieceKind::J => [[-1, 0], [0, 0], [1, 0], [-1, 1]],
+            PieceKind::L => [[-1, 0], [0, 0], [1, 0], [1, 1]],
+        }
+    }
+
+    /// Cells covered by the piece relative to its position.
+    pub const fn rotation(self, spin: Spin) -> [[i8; 2]; 4] {
+        let mut cells = self.base_shape();
+        let mut steps = spin.0 % 4;
+        let bbox_is_even = (1 - self.base_width() % 2) as i8;
+
+        while steps > 0 {
+            let mut i = 0;
+
+            while i < 4 {
+                cells[i] = [cells[i][1], -cells[i][0] - bbox_is_even];
+                i += 1;
+            }
+
+            steps -= 1;
+        }
+
+        cells
+    }
+
+    pub const fn base_width(self) -> u8 {
+        match self {
+            PieceKind::I => 4,
+            PieceKind::O => 2,
+            PieceKind::T | PieceKind::S | PieceKind::Z | PieceKind::J | PieceKind::L => 3,
+        }
+    }
+
+    pub fn piece_covered_cells(self, pos: GridPos, spin: Spin) -> impl Iterator<Item = GridPos> {
+        self.rotation(spin).into_iter().map(move |[x, y]| GridPos {
+            x: pos.x.wrapping_add_signed(x),
+            y: pos.y.wrapping_add_signed(y),
+        })
+    }
+
+    pub const fn as_char(self) -> char {
+        match self {
+            PieceKind::I => 'I',
+            PieceKind::O => 'O',
+            PieceKind::T => 'T',
+            PieceKind::S => 'S',
+            PieceKind::Z => 'Z',
+            PieceKind::J => 'J',
+            PieceKind::L => 'L',
+        }
+    }
+}
+
+impl TryFrom<char> for PieceKind {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        Self::all()
+            .into_iter()
+            .find(|kind| kind.as_char() == value.to_ascii_uppercase())
+            .ok_or(value)
+    }
+}
+
+// -- GridPos
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GridPos {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl std::fmt::Display for GridPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
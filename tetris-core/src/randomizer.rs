@@ -0,0 +1,288 @@
+//! Generators for the sequence of pieces.
+//! See https://tetris.wiki/Random_Generator
+//!
+//! All randomizers are built from an explicit seed and only rely on portable
+//! random number generation, so that a seed produces the same sequence of
+//! pieces on every platform.
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::piece::PieceKind;
+
+pub trait Randomizer: Send + Sync {
+    /// Draw the next piece of the sequence.
+    fn next_piece(&mut self) -> PieceKind;
+}
+
+// -- RandomizerKind
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RandomizerKind {
+    /// Shuffle bags containing one copy of each piece
+    #[default]
+    SevenBag,
+    /// Shuffle bags containing two copies of each piece
+    FourteenBag,
+    /// Draw each piece independently
+    PureRandom,
+    /// Avoid pieces from the 4 last ones, as in TGM
+    History4,
+    /// Repeat a fixed sequence of pieces
+    Scripted(Vec<PieceKind>),
+}
+
+impl RandomizerKind {
+    /// Randomizers that don't need any parameter.
+    pub fn presets() -> [Self; 4] {
+        [
+            Self::SevenBag,
+            Self::FourteenBag,
+            Self::PureRandom,
+            Self::History4,
+        ]
+    }
+
+    pub fn build(&self, seed: u64) -> Box<dyn Randomizer> {
+        match self {
+            Self::SevenBag => Box::new(BagRandomizer::new(seed, 1)),
+            Self::FourteenBag => Box::new(BagRandomizer::new(seed, 2)),
+            Self::PureRandom => Box::new(PureRandomizer::new(seed)),
+            Self::History4 => Box::new(HistoryRandomizer::new(seed, 4)),
+            Self::Scripted(sequence) => Box::new(ScriptedRandomizer::new(sequence.clone())),
+        }
+    }
+}
+
+impl Display for RandomizerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SevenBag => write!(f, "7-bag"),
+            Self::FourteenBag => write!(f, "14-bag"),
+            Self::PureRandom => write!(f, "random"),
+            Self::History4 => write!(f, "history-4"),
+            Self::Scripted(sequence) => {
+                write!(f, "scripted:")?;
+
+                for kind in sequence {
+                    write!(f, "{}", kind.as_char())?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7-bag" => Ok(Self::SevenBag),
+            "14-bag" => Ok(Self::FourteenBag),
+            "random" => Ok(Self::PureRandom),
+            "history-4" => Ok(Self::History4),
+            _ => {
+                let Some(sequence) = s.strip_prefix("scripted:") else {
+                    return Err(format!("unknown randomizer `{s}`"));
+                };
+
+                let sequence: Vec<_> = sequence
+                    .chars()
+                    .map(PieceKind::try_from)
+                    .collect::<Result<_, _>>()
+                    .map_err(|c| format!("unknown piece `{c}`"))?;
+
+                if sequence.is_empty() {
+                    return Err("scripted sequence can't be empty".to_string());
+                }
+
+                Ok(Self::Scripted(sequence))
+            }
+        }
+    }
+}
+
+/// Pick a piece uniformly, sampling in `u32` to stay independent from the
+/// target's pointer width.
+fn random_piece(rng: &mut ChaCha8Rng) -> PieceKind {
+    let pieces = PieceKind::all();
+    pieces[rng.gen_range(0..pieces.len() as u32) as usize]
+}
+
+// -- BagRandomizer
+
+pub struct BagRandomizer {
+    rng: ChaCha8Rng,
+    copies: usize,
+    bag: Vec<PieceKind>,
+}
+
+impl BagRandomizer {
+    pub fn new(seed: u64, copies: usize) -> Self {
+        assert!(
+            copies > 0,
+            "A bag must contain at least one copy of each piece"
+        );
+
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            copies,
+            bag: Vec::with_capacity(copies * PieceKind::all().len()),
+        }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next_piece(&mut self) -> PieceKind {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&PieceKind::all());
+            }
+
+            self.bag.shuffle(&mut self.rng);
+        }
+
+        self.bag.pop().unwrap()
+    }
+}
+
+// -- PureRandomizer
+
+pub struct PureRandomizer {
+    rng: ChaCha8Rng,
+}
+
+impl PureRandomizer {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Randomizer for PureRandomizer {
+    fn next_piece(&mut self) -> PieceKind {
+        random_piece(&mut self.rng)
+    }
+}
+
+// -- HistoryRandomizer
+
+/// Draw a random piece and reroll it a few times if it belongs to the 4 last
+/// pieces.
+/// See https://tetris.wiki/TGM_randomizer
+pub struct HistoryRandomizer {
+    rng: ChaCha8Rng,
+    tries: u8,
+    history: [PieceKind; 4],
+    first: bool,
+}
+
+impl HistoryRandomizer {
+    pub fn new(seed: u64, tries: u8) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            tries,
+            history: [PieceKind::Z; 4],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for HistoryRandomizer {
+    fn next_piece(&mut self) -> PieceKind {
+        let kind = {
+            if self.first {
+                // The first piece is never an S, Z or O
+                self.first = false;
+                [PieceKind::I, PieceKind::T, PieceKind::J, PieceKind::L]
+                    [self.rng.gen_range(0..4u32) as usize]
+            } else {
+                let mut kind = random_piece(&mut self.rng);
+
+                for _ in 1..self.tries {
+                    if !self.history.contains(&kind) {
+                        break;
+                    }
+
+                    kind = random_piece(&mut self.rng);
+                }
+
+                kind
+            }
+        };
+
+        self.history.rotate_right(1);
+        self.history[0] = kind;
+        kind
+    }
+}
+
+// -- ScriptedRandomizer
+
+/// Repeat a predefined sequence, mostly useful for tests and puzzles.
+pub struct ScriptedRandomizer {
+    sequence: Vec<PieceKind>,
+    index: usize,
+}
+
+impl ScriptedRandomizer {
+    pub fn new(sequence: Vec<PieceKind>) -> Self {
+        assert!(!sequence.is_empty(), "Scripted sequence can't be empty");
+        Self { sequence, index: 0 }
+    }
+}
+
+impl Randomizer for ScriptedRandomizer {
+    fn next_piece(&mut self) -> PieceKind {
+        let kind = self.sequence[self.index];
+        self.index = (self.index + 1) % self.sequence.len();
+        kind
+    }
+}
+
+// -- PieceQueue
+
+/// Number of upcoming pieces that are known in advance by the queue.
+pub const PIECE_LOOKAHEAD: usize = 6;
+
+/// Sequence of pieces drawn from a randomizer, which can be peeked ahead.
+pub struct PieceQueue {
+    randomizer: Box<dyn Randomizer>,
+    /// Upcoming pieces, in order of appearance
+    lookahead: VecDeque<PieceKind>,
+}
+
+impl PieceQueue {
+    pub fn new(randomizer: Box<dyn Randomizer>) -> Self {
+        Self {
+            randomizer,
+            lookahead: VecDeque::with_capacity(PIECE_LOOKAHEAD + 1),
+        }
+    }
+
+    fn fill_lookahead(&mut self, len: usize) {
+        while self.lookahead.len() < len {
+            let kind = self.randomizer.next_piece();
+            self.lookahead.push_back(kind);
+        }
+    }
+
+    pub fn choose(&mut self) -> PieceKind {
+        self.fill_lookahead(PIECE_LOOKAHEAD + 1);
+        self.lookahead.pop_front().unwrap()
+    }
+
+    /// Iterate over the `n` next pieces that will be chosen
+    pub fn peek_n(&mut self, n: usize) -> impl Iterator<Item = PieceKind> + '_ {
+        self.fill_lookahead(n);
+        self.lookahead.iter().copied().take(n)
+    }
+}
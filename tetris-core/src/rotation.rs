@@ -0,0 +1,123 @@
+//! Super Rotation System: translations tried when a rotated piece overlaps
+//! with the walls or filled cells.
+//! See https://tetris.wiki/Super_Rotation_System
+//!
+//! Tables use a y axis pointing upward and are indexed by the spin of the
+//! piece before rotation. The first test of each transition is always the
+//! rotation in place.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::piece::{PieceKind, Spin};
+
+type KickTable = [[[i8; 2]; 5]; 4];
+
+const JLSTZ_KICKS_CW: KickTable = [
+    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]], // 0 -> R
+    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],     // R -> 2
+    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],    // 2 -> L
+    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],  // L -> 0
+];
+
+const JLSTZ_KICKS_CCW: KickTable = [
+    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],    // 0 -> L
+    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],     // R -> 0
+    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]], // 2 -> R
+    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],  // L -> 2
+];
+
+const I_KICKS_CW: KickTable = [
+    [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]], // 0 -> R
+    [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]], // R -> 2
+    [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]], // 2 -> L
+    [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]], // L -> 0
+];
+
+const I_KICKS_CCW: KickTable = [
+    [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]], // 0 -> L
+    [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]], // R -> 0
+    [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]], // 2 -> R
+    [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]], // L -> 2
+];
+
+/// Same table for all pieces, as in TETR.IO's SRS+.
+const TETRIO_KICKS_180: [[[i8; 2]; 6]; 4] = [
+    [[0, 0], [0, 1], [1, 1], [-1, 1], [1, 0], [-1, 0]], // 0 -> 2
+    [[0, 0], [1, 0], [1, 2], [1, 1], [0, 2], [0, 1]],   // R -> L
+    [[0, 0], [0, -1], [-1, -1], [1, -1], [-1, 0], [1, 0]], // 2 -> 0
+    [[0, 0], [-1, 0], [-1, 2], [-1, 1], [0, 2], [0, 1]], // L -> R
+];
+
+// -- Rotation
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+impl Rotation {
+    /// Spin of a piece after this rotation is applied.
+    pub const fn apply(self, spin: Spin) -> Spin {
+        let delta = match self {
+            Self::Clockwise => 1,
+            Self::CounterClockwise => 3,
+            Self::Half => 2,
+        };
+
+        Spin((spin.0 + delta) % 4)
+    }
+}
+
+// -- Kicks180
+
+/// Kick table used for 180° rotations, which are not part of the guideline.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Kicks180 {
+    /// Table from TETR.IO's SRS+
+    #[default]
+    Tetrio,
+    /// Only rotate in place
+    NoKicks,
+}
+
+impl Display for Kicks180 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tetrio => write!(f, "tetrio"),
+            Self::NoKicks => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for Kicks180 {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tetrio" => Ok(Self::Tetrio),
+            "none" => Ok(Self::NoKicks),
+            _ => Err(format!("unknown 180 kick table `{s}`")),
+        }
+    }
+}
+
+impl PieceKind {
+    /// Translations to test, in order, when rotating a piece from given spin.
+    pub fn kicks(self, from: Spin, rotation: Rotation, kicks_180: Kicks180) -> &'static [[i8; 2]] {
+        let from = usize::from(from.0 % 4);
+
+        match (self, rotation, kicks_180) {
+            // The O piece never moves when it rotates
+            (Self::O, _, _) | (_, Rotation::Half, Kicks180::NoKicks) => &[[0, 0]],
+            (_, Rotation::Half, Kicks180::Tetrio) => &TETRIO_KICKS_180[from],
+            (Self::I, Rotation::Clockwise, _) => &I_KICKS_CW[from],
+            (Self::I, Rotation::CounterClockwise, _) => &I_KICKS_CCW[from],
+            (_, Rotation::Clockwise, _) => &JLSTZ_KICKS_CW[from],
+            (_, Rotation::CounterClockwise, _) => &JLSTZ_KICKS_CCW[from],
+        }
+    }
+}
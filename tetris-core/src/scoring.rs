@@ -0,0 +1,249 @@
+//! Score awards and level progression.
+//! See https://tetris.wiki/Scoring
+
+use std::time::Duration;
+
+use crate::board::TSpin;
+
+// -- LineClear
+
+/// Lines cleared by a single piece, along with how they were cleared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineClear {
+    pub lines: u8,
+    pub t_spin: TSpin,
+    /// The grid is empty once lines are cleared
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Difficult clears are rewarded when they are chained back-to-back.
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.t_spin != TSpin::None)
+    }
+}
+
+// -- ScoringRules
+
+/// Points awarded for each kind of clear, before they are multiplied by the
+/// level. All tables are indexed by the number of cleared lines.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoringRules {
+    pub lines: [u64; 5],
+    pub t_spin_mini: [u64; 3],
+    pub t_spin: [u64; 4],
+    /// Points of difficult clears continuing a chain, in percents
+    pub back_to_back_percent: u64,
+    /// Points for each clear in a row after the first one
+    pub combo: u64,
+    pub perfect_clear: [u64; 5],
+    /// Replaces the perfect clear award of a back-to-back tetris
+    pub perfect_clear_back_to_back: u64,
+    /// Points per row, which don't depend on the level
+    pub soft_drop: u64,
+    pub hard_drop: u64,
+}
+
+impl ScoringRules {
+    /// See https://tetris.wiki/Scoring#Recent_guideline_compatible_games
+    pub const GUIDELINE: Self = Self {
+        lines: [0, 100, 300, 500, 800],
+        t_spin_mini: [100, 200, 400],
+        t_spin: [400, 800, 1200, 1600],
+        back_to_back_percent: 150,
+        combo: 50,
+        perfect_clear: [0, 800, 1200, 1800, 2000],
+        perfect_clear_back_to_back: 3200,
+        soft_drop: 1,
+        hard_drop: 2,
+    };
+
+    /// Original Nintendo scoring, which only rewards the number of lines.
+    pub const CLASSIC: Self = Self {
+        lines: [0, 40, 100, 300, 1200],
+        t_spin_mini: [0, 40, 100],
+        t_spin: [0, 40, 100, 300],
+        back_to_back_percent: 100,
+        combo: 0,
+        perfect_clear: [0; 5],
+        perfect_clear_back_to_back: 0,
+        soft_drop: 1,
+        hard_drop: 0,
+    };
+
+    /// Points of a clear, ignoring chains.
+    pub fn base_points(&self, clear: &LineClear) -> u64 {
+        let table: &[u64] = match clear.t_spin {
+            TSpin::None => &self.lines,
+            TSpin::Mini => &self.t_spin_mini,
+            TSpin::Full => &self.t_spin,
+        };
+
+        table[usize::from(clear.lines).min(table.len() - 1)]
+    }
+
+    /// Points for rows a piece went through with soft or hard drops.
+    pub fn drop_points(&self, soft_drop_rows: u32, hard_drop_rows: u32) -> u64 {
+        self.soft_drop * u64::from(soft_drop_rows) + self.hard_drop * u64::from(hard_drop_rows)
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self::GUIDELINE
+    }
+}
+
+// -- ScoringState
+
+/// Points awarded for a piece lock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Award {
+    pub points: u64,
+    /// Number of clears in a row before this one
+    pub combo: u32,
+    /// The clear continued a chain of difficult clears
+    pub back_to_back: bool,
+}
+
+/// Chains of clears that are rewarded with bonus points.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScoringState {
+    /// Number of consecutive locks that cleared lines
+    pub combo: u32,
+    /// Number of consecutive difficult clears, locks without lines are ignored
+    pub back_to_back: u32,
+}
+
+impl ScoringState {
+    /// Update chains after a piece locked and compute awarded points.
+    pub fn register(&mut self, rules: &ScoringRules, clear: &LineClear, level: u32) -> Award {
+        let mut award = Award {
+            points: rules.base_points(clear),
+            ..Default::default()
+        };
+
+        if clear.lines == 0 {
+            self.combo = 0;
+            award.points *= u64::from(level);
+            return award;
+        }
+
+        award.combo = self.combo;
+        self.combo += 1;
+
+        if clear.is_difficult() {
+            award.back_to_back = self.back_to_back > 0;
+            self.back_to_back += 1;
+        } else {
+            self.back_to_back = 0;
+        }
+
+        if award.back_to_back {
+            award.points = award.points * rules.back_to_back_percent / 100;
+        }
+
+        award.points += rules.combo * u64::from(award.combo);
+
+        if clear.perfect_clear {
+            award.points += {
+                if award.back_to_back && clear.lines >= 4 {
+                    rules.perfect_clear_back_to_back
+                } else {
+                    rules.perfect_clear[usize::from(clear.lines).min(4)]
+                }
+            };
+        }
+
+        award.points *= u64::from(level);
+        award
+    }
+}
+
+// -- LevelGoal
+
+/// Number of lines to clear to complete a level.
+/// See https://tetris.wiki/Marathon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LevelGoal {
+    /// Every level requires 10 cleared lines
+    #[default]
+    Fixed,
+    /// Level `n` requires `5 * n` lines, which are awarded by clear type
+    Variable,
+}
+
+impl LevelGoal {
+    /// Lines required to complete given level.
+    pub fn lines(self, level: u32) -> u32 {
+        match self {
+            Self::Fixed => 10,
+            Self::Variable => 5 * level,
+        }
+    }
+
+    /// Lines counted toward the goal for a clear.
+    pub fn awarded_lines(self, clear: &LineClear, back_to_back: bool) -> u32 {
+        if self == Self::Fixed {
+            return clear.lines.into();
+        }
+
+        let lines = match (clear.t_spin, clear.lines) {
+            (TSpin::None, lines) => [0, 1, 3, 5, 8][usize::from(lines).min(4)],
+            (TSpin::Mini, 0) => 1,
+            (TSpin::Mini, _) => 2,
+            (TSpin::Full, lines) => [4, 8, 12, 16][usize::from(lines).min(3)],
+        };
+
+        // Back-to-back clears award half of their lines as a bonus
+        if back_to_back && clear.is_difficult() {
+            lines + lines / 2
+        } else {
+            lines
+        }
+    }
+}
+
+// -- LevelProgress
+
+/// Current level along with lines awarded toward its goal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelProgress {
+    pub level: u32,
+    /// Lines awarded since the level started
+    pub lines: u32,
+}
+
+impl LevelProgress {
+    pub fn new(level: u32) -> Self {
+        Self { level, lines: 0 }
+    }
+
+    /// Count awarded lines toward the goal, levelling up as many times as
+    /// the goal is reached.
+    pub fn award(&mut self, goal: LevelGoal, lines: u32) {
+        self.lines += lines;
+
+        while self.lines >= goal.lines(self.level) {
+            self.lines -= goal.lines(self.level);
+            self.level += 1;
+        }
+    }
+}
+
+/// Gravity is capped to 20 rows per frame at 60 frames per second, at which
+/// pieces fall instantly to the bottom of the grid.
+pub const MIN_TIME_PER_ROW: Duration = Duration::from_nanos(1_000_000_000 / (20 * 60));
+
+/// Time for a piece to fall by one row.
+/// See https://tetris.fandom.com/wiki/Tetris_Worlds#Gravity
+pub fn time_per_row(level: u32) -> Duration {
+    let exp = level.saturating_sub(1);
+
+    // Base of the formula gets negative after level 115
+    let base = (0.8 - (f64::from(exp) * 0.007)).max(0.0);
+    let secs = base.powi(i32::try_from(exp).unwrap_or(i32::MAX));
+    Duration::from_secs_f64(secs).max(MIN_TIME_PER_ROW)
+}
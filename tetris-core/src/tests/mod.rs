@@ -0,0 +1,814 @@
+use crate::bitboard::PieceMask;
+use crate::board::{Board, BoardConfig, TSpin, GRID_HEIGHT, GRID_VISIBLE_HEIGHT, GRID_WIDTH};
+use crate::bot::{self, Bot, Features, Weights};
+use crate::input::PlayerInput;
+use crate::piece::{GridPos, PieceKind, Spin};
+use crate::randomizer::{PieceQueue, RandomizerKind};
+use crate::rotation::{Kicks180, Rotation};
+use crate::scoring::{
+    self, LevelGoal, LevelProgress, LineClear, ScoringRules, ScoringState, MIN_TIME_PER_ROW,
+};
+
+/// Build a board from its rows, given from top to bottom, where `#` marks a
+/// filled cell.
+fn board_from_rows(rows: &[&str]) -> Board {
+    let mut board = Board::default();
+
+    for (y, row) in rows.iter().rev().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == '#' {
+                let pos = GridPos {
+                    x: x.try_into().unwrap(),
+                    y: y.try_into().unwrap(),
+                };
+
+                board.set(&pos, Some(PieceKind::I));
+            }
+        }
+    }
+
+    board
+}
+
+fn draw_sequence(kind: &RandomizerKind, seed: u64, len: usize) -> String {
+    let mut randomizer = kind.build(seed);
+    (0..len)
+        .map(|_| randomizer.next_piece().as_char())
+        .collect()
+}
+
+#[test]
+fn test_piece_queue_lookahead() {
+    let mut generator = PieceQueue::new(RandomizerKind::SevenBag.build(0));
+    generator.choose();
+
+    // Look further than the end of current bag
+    let peeked: Vec<_> = generator.peek_n(10).collect();
+    let chosen: Vec<_> = (0..10).map(|_| generator.choose()).collect();
+    assert_eq!(peeked, chosen);
+
+    // Each bag still contains all pieces
+    let mut generator = PieceQueue::new(RandomizerKind::SevenBag.build(0));
+    let mut bag: Vec<_> = generator.peek_n(14).skip(7).collect();
+    bag.sort_by_key(|&kind| kind as u8);
+    assert_eq!(bag, PieceKind::all());
+}
+
+#[test]
+fn test_randomizers_are_reproducible() {
+    for kind in [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::PureRandom,
+        RandomizerKind::History4,
+    ] {
+        assert_eq!(draw_sequence(&kind, 7, 100), draw_sequence(&kind, 7, 100));
+        assert_ne!(draw_sequence(&kind, 7, 100), draw_sequence(&kind, 8, 100));
+    }
+
+    // Sequences must not depend on the platform, see `random_piece`
+    assert_eq!(
+        draw_sequence(&RandomizerKind::SevenBag, 42, 14),
+        "OZTLJSIOZJTLSI",
+    );
+
+    assert_eq!(
+        draw_sequence(&RandomizerKind::PureRandom, 42, 14),
+        "OZOLJTZJTZOTTO",
+    );
+}
+
+#[test]
+fn test_bag_randomizers() {
+    for (kind, copies) in [
+        (RandomizerKind::SevenBag, 1),
+        (RandomizerKind::FourteenBag, 2),
+    ] {
+        let sequence = draw_sequence(&kind, 1, 7 * copies * 10);
+
+        for bag in sequence.as_bytes().chunks(7 * copies) {
+            for piece in PieceKind::all() {
+                let count = bag.iter().filter(|&&c| c == piece.as_char() as u8).count();
+                assert_eq!(count, copies, "{kind} bag {bag:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_history_randomizer() {
+    for seed in 0..100 {
+        let sequence = draw_sequence(&RandomizerKind::History4, seed, 1);
+        assert!(!["S", "Z", "O"].contains(&sequence.as_str()));
+    }
+}
+
+#[test]
+fn test_scripted_randomizer() {
+    let kind: RandomizerKind = "scripted:TIo".parse().unwrap();
+    assert_eq!(kind.to_string(), "scripted:TIO");
+    assert_eq!(draw_sequence(&kind, 0, 7), "TIOTIOT");
+    assert!("scripted:".parse::<RandomizerKind>().is_err());
+    assert!("scripted:TX".parse::<RandomizerKind>().is_err());
+}
+
+#[test]
+fn test_board_moves_and_locks() {
+    let mut board = Board::default();
+    let kind = PieceKind::O;
+    let mut pos = board.config().spawn_position(kind);
+    assert!(!board.conflicts(kind, pos, Spin(0)));
+
+    // Walls block horizontal moves
+    while board.try_move([-1, 0], kind, &mut pos, Spin(0)) {}
+    assert_eq!(pos.x, 1);
+
+    assert_eq!(board.drop(kind, &mut pos, Spin(0)), GRID_VISIBLE_HEIGHT);
+    assert!(!board.lock(kind, pos, Spin(0)));
+    assert!(board.is_filled(&GridPos { x: 0, y: 0 }));
+    assert!(board.is_filled(&GridPos { x: 1, y: 1 }));
+    assert_eq!(board.get(&GridPos { x: 0, y: 0 }), Some(PieceKind::O));
+
+    // Next piece lands on top of the previous one
+    let mut pos = GridPos { x: 1, y: 10 };
+    assert_eq!(board.drop(kind, &mut pos, Spin(0)), 7);
+}
+
+#[test]
+fn test_board_clear_rows() {
+    let mut board = Board::default();
+
+    for x in 0..GRID_WIDTH {
+        board.set(&GridPos { x, y: 0 }, Some(PieceKind::I));
+        board.set(&GridPos { x, y: 2 }, Some(PieceKind::I));
+    }
+
+    board.set(&GridPos { x: 3, y: 1 }, Some(PieceKind::T));
+    board.set(&GridPos { x: 4, y: 3 }, Some(PieceKind::S));
+    assert_eq!(board.completed_rows(), [0, 2]);
+
+    board.clear_rows(&[0, 2]);
+    assert!(board.completed_rows().is_empty());
+    assert_eq!(board.get(&GridPos { x: 3, y: 0 }), Some(PieceKind::T));
+    assert_eq!(board.get(&GridPos { x: 4, y: 1 }), Some(PieceKind::S));
+    assert!(!board.is_filled(&GridPos { x: 4, y: 3 }));
+}
+
+/// Offsets of each spin in the SRS, from which kicks are derived.
+/// See https://tetris.wiki/Super_Rotation_System#How_Guideline_SRS_Really_Works
+const SRS_JLSTZ_OFFSETS: [[[i8; 2]; 5]; 4] = [
+    [[0, 0], [0, 0], [0, 0], [0, 0], [0, 0]],
+    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+    [[0, 0], [0, 0], [0, 0], [0, 0], [0, 0]],
+    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+];
+
+const SRS_I_OFFSETS: [[[i8; 2]; 5]; 4] = [
+    [[0, 0], [-1, 0], [2, 0], [-1, 0], [2, 0]],
+    [[-1, 0], [0, 0], [0, 0], [0, 1], [0, -2]],
+    [[-1, 1], [1, 1], [-2, 1], [1, 0], [-2, 0]],
+    [[0, 1], [0, 1], [0, 1], [0, -1], [0, 2]],
+];
+
+/// 180 kicks as found in TETR.IO's sources, with y axis pointing downward and
+/// without the rotation in place.
+const TETRIO_180_REFERENCE: [[[i8; 2]; 5]; 4] = [
+    [[0, -1], [1, -1], [-1, -1], [1, 0], [-1, 0]],
+    [[1, 0], [1, -2], [1, -1], [0, -2], [0, -1]],
+    [[0, 1], [-1, 1], [1, 1], [-1, 0], [1, 0]],
+    [[-1, 0], [-1, -2], [-1, -1], [0, -2], [0, -1]],
+];
+
+/// Reference kicks of the SRS for a quarter turn, which are the difference of
+/// offsets between both spins. The I piece rotates around a cell in this
+/// formulation, which is compensated by the first test.
+fn srs_reference_kicks(kind: PieceKind, from: Spin, to: Spin) -> Vec<[i8; 2]> {
+    let offsets = match kind {
+        PieceKind::O => return vec![[0, 0]],
+        PieceKind::I => SRS_I_OFFSETS,
+        _ => SRS_JLSTZ_OFFSETS,
+    };
+
+    let tests: Vec<_> = (0..5)
+        .map(|i| {
+            let [fx, fy] = offsets[usize::from(from.0)][i];
+            let [tx, ty] = offsets[usize::from(to.0)][i];
+            [fx - tx, fy - ty]
+        })
+        .collect();
+
+    let [x0, y0] = tests[0];
+    tests.iter().map(|[x, y]| [x - x0, y - y0]).collect()
+}
+
+#[test]
+fn test_srs_kicks_match_reference() {
+    for kind in PieceKind::all() {
+        for from in (0..4).map(Spin) {
+            for rotation in [Rotation::Clockwise, Rotation::CounterClockwise] {
+                let to = rotation.apply(from);
+
+                assert_eq!(
+                    kind.kicks(from, rotation, Kicks180::Tetrio),
+                    srs_reference_kicks(kind, from, to),
+                    "{kind:?} {from:?} -> {to:?}",
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_srs_kicks_are_reversible() {
+    for kind in PieceKind::all() {
+        for from in (0..4).map(Spin) {
+            let to = Rotation::Clockwise.apply(from);
+            let back = kind.kicks(to, Rotation::CounterClockwise, Kicks180::Tetrio);
+
+            for (i, [x, y]) in kind
+                .kicks(from, Rotation::Clockwise, Kicks180::Tetrio)
+                .iter()
+                .enumerate()
+            {
+                assert_eq!(back[i], [-x, -y], "{kind:?} {from:?} test {i}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_180_kicks_match_reference() {
+    for kind in PieceKind::all() {
+        for from in (0..4).map(Spin) {
+            let kicks = kind.kicks(from, Rotation::Half, Kicks180::Tetrio);
+            let no_kicks = kind.kicks(from, Rotation::Half, Kicks180::NoKicks);
+            assert_eq!(no_kicks, [[0, 0]]);
+
+            if kind == PieceKind::O {
+                assert_eq!(kicks, [[0, 0]]);
+                continue;
+            }
+
+            let reference: Vec<_> = std::iter::once([0, 0])
+                .chain(TETRIO_180_REFERENCE[usize::from(from.0)].map(|[x, y]| [x, -y]))
+                .collect();
+
+            assert_eq!(kicks, reference, "{kind:?} {from:?}");
+        }
+    }
+}
+
+#[test]
+fn test_rotation_wall_kick() {
+    let board = Board::default();
+    let mut pos = GridPos { x: 0, y: 5 };
+    let mut spin = Spin(1);
+
+    // Vertical T against the left wall is pushed back into the grid
+    let (cw, ccw) = (Rotation::Clockwise, Rotation::CounterClockwise);
+    assert!(board.conflicts(PieceKind::T, pos, Spin(2)));
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, cw, Kicks180::Tetrio);
+    assert_eq!(kick, Some(1));
+    assert_eq!(spin, Spin(2));
+    assert_eq!(pos, GridPos { x: 1, y: 5 });
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, ccw, Kicks180::Tetrio);
+    assert_eq!(kick, Some(0));
+    assert_eq!(spin, Spin(1));
+}
+
+#[test]
+fn test_rotation_180() {
+    let board = Board::default();
+    let half = Rotation::Half;
+
+    // Flat T on the floor is lifted when flipped
+    let mut pos = GridPos { x: 4, y: 0 };
+    let mut spin = Spin(0);
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, half, Kicks180::Tetrio);
+    assert_eq!(kick, Some(1));
+    assert_eq!((pos, spin), (GridPos { x: 4, y: 1 }, Spin(2)));
+
+    let mut pos = GridPos { x: 4, y: 0 };
+    let mut spin = Spin(0);
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, half, Kicks180::NoKicks);
+    assert_eq!(kick, None);
+    assert_eq!((pos, spin), (GridPos { x: 4, y: 0 }, Spin(0)));
+}
+
+#[test]
+fn test_t_spin_double() {
+    let mut board = board_from_rows(&[
+        "###.......", //
+        "##...#####",
+        "###.######",
+    ]);
+
+    // Rotate a vertical T into the slot
+    let mut pos = GridPos { x: 3, y: 1 };
+    let mut spin = Spin(1);
+    let cw = Rotation::Clockwise;
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, cw, Kicks180::Tetrio);
+    assert_eq!(kick, Some(0));
+    let rotation = kick.map(|kick| (cw, kick));
+    assert_eq!(board.t_spin(PieceKind::T, pos, spin, rotation), TSpin::Full);
+
+    // Same position is not a T-spin if not reached with a rotation
+    assert_eq!(board.t_spin(PieceKind::T, pos, spin, None), TSpin::None);
+    assert_eq!(board.t_spin(PieceKind::J, pos, spin, rotation), TSpin::None);
+
+    board.lock(PieceKind::T, pos, spin);
+    assert_eq!(board.completed_rows(), [0, 1]);
+}
+
+#[test]
+fn test_t_spin_mini() {
+    let board = board_from_rows(&[
+        "#.........", //
+        "...#######",
+    ]);
+
+    // Only one of the corners in front of the T is blocked, the floor
+    // blocks both corners behind it
+    let pos = GridPos { x: 1, y: 0 };
+    assert!(!board.conflicts(PieceKind::T, pos, Spin(0)));
+    let cw = Rotation::Clockwise;
+    assert_eq!(
+        board.t_spin(PieceKind::T, pos, Spin(0), Some((cw, 0))),
+        TSpin::Mini
+    );
+
+    // Kicking with the last test is always a full T-spin
+    assert_eq!(
+        board.t_spin(PieceKind::T, pos, Spin(0), Some((cw, 4))),
+        TSpin::Full
+    );
+
+    // Two corners are not enough
+    let board = board_from_rows(&["...#######"]);
+    assert_eq!(
+        board.t_spin(PieceKind::T, pos, Spin(0), Some((cw, 0))),
+        TSpin::None
+    );
+}
+
+#[test]
+fn test_t_spin_triple_kick() {
+    let mut board = board_from_rows(&[
+        "...#......", //
+        "..........",
+        "###.######",
+        "##..######",
+        "###.######",
+    ]);
+
+    // The T can only enter the slot with the last kick
+    let mut pos = GridPos { x: 2, y: 3 };
+    let mut spin = Spin(0);
+    assert!(!board.conflicts(PieceKind::T, pos, spin));
+    let ccw = Rotation::CounterClockwise;
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, ccw, Kicks180::Tetrio);
+    assert_eq!(kick, Some(4));
+    assert_eq!((pos, spin), (GridPos { x: 3, y: 1 }, Spin(3)));
+    let rotation = kick.map(|kick| (ccw, kick));
+    assert_eq!(board.t_spin(PieceKind::T, pos, spin, rotation), TSpin::Full);
+
+    board.lock(PieceKind::T, pos, spin);
+    assert_eq!(board.completed_rows(), [0, 1, 2]);
+}
+
+#[test]
+fn test_t_spin_180_kick() {
+    let board = board_from_rows(&[
+        ".....#....", //
+        "..........",
+        "...#.#....",
+        "...#......",
+        "..........",
+    ]);
+
+    // The fifth kick of the 180° table moves the T two rows up, next to
+    // both corners behind it but to a single one in front of it
+    let mut pos = GridPos { x: 4, y: 1 };
+    let mut spin = Spin(1);
+    let half = Rotation::Half;
+    let kick = board.try_rotate(PieceKind::T, &mut pos, &mut spin, half, Kicks180::Tetrio);
+    assert_eq!(kick, Some(4));
+    assert_eq!((pos, spin), (GridPos { x: 4, y: 3 }, Spin(3)));
+
+    // It is not the triple kick of SRS
+    let rotation = kick.map(|kick| (half, kick));
+    assert_eq!(board.t_spin(PieceKind::T, pos, spin, rotation), TSpin::Mini);
+
+    let ccw = Rotation::CounterClockwise;
+    assert_eq!(
+        board.t_spin(PieceKind::T, pos, spin, Some((ccw, 4))),
+        TSpin::Full
+    );
+}
+
+#[test]
+fn test_scoring() {
+    let rules = ScoringRules::GUIDELINE;
+    assert_eq!(scoring::time_per_row(1).as_secs(), 1);
+    assert!(scoring::time_per_row(2) < scoring::time_per_row(1));
+    assert_eq!(scoring::time_per_row(20), MIN_TIME_PER_ROW);
+    assert_eq!(scoring::time_per_row(200), MIN_TIME_PER_ROW);
+    assert_eq!(scoring::time_per_row(u32::MAX), MIN_TIME_PER_ROW);
+
+    let base_points = |lines, t_spin| {
+        rules.base_points(&LineClear {
+            lines,
+            t_spin,
+            perfect_clear: false,
+        })
+    };
+
+    assert_eq!(base_points(0, TSpin::None), 0);
+    assert_eq!(base_points(1, TSpin::None), 100);
+    assert_eq!(base_points(4, TSpin::None), 800);
+    assert_eq!(base_points(0, TSpin::Mini), 100);
+    assert_eq!(base_points(1, TSpin::Mini), 200);
+    assert_eq!(base_points(0, TSpin::Full), 400);
+    assert_eq!(base_points(2, TSpin::Full), 1200);
+    assert_eq!(base_points(3, TSpin::Full), 1600);
+    assert_eq!(rules.drop_points(3, 10), 23);
+}
+
+#[test]
+fn test_scoring_chains() {
+    let rules = ScoringRules::GUIDELINE;
+    let mut state = ScoringState::default();
+
+    let mut lock = |lines, t_spin| {
+        let clear = LineClear {
+            lines,
+            t_spin,
+            perfect_clear: false,
+        };
+
+        let award = state.register(&rules, &clear, 2);
+        (award.points, award.combo, award.back_to_back)
+    };
+
+    assert_eq!(lock(4, TSpin::None), (1600, 0, false));
+    assert_eq!(lock(2, TSpin::Full), (2 * (1800 + 50), 1, true));
+    assert_eq!(lock(0, TSpin::None), (0, 0, false));
+
+    // Zero-line T-spins break combos but keep back-to-back chains
+    assert_eq!(lock(0, TSpin::Full), (800, 0, false));
+    assert_eq!(lock(1, TSpin::Mini), (2 * 300, 0, true));
+    assert_eq!(lock(1, TSpin::None), (2 * (100 + 50), 1, false));
+    assert_eq!(lock(4, TSpin::None), (2 * (800 + 100), 2, false));
+
+    // Classic rules don't reward chains
+    let mut state = ScoringState::default();
+    let clear = LineClear {
+        lines: 4,
+        ..Default::default()
+    };
+
+    state.register(&ScoringRules::CLASSIC, &clear, 1);
+    let award = state.register(&ScoringRules::CLASSIC, &clear, 1);
+    assert_eq!(award.points, 1200);
+}
+
+#[test]
+fn test_perfect_clear() {
+    let rules = ScoringRules::GUIDELINE;
+    let mut board = board_from_rows(&["########.."]);
+    assert!(!board.is_perfect_clear());
+
+    board.lock(PieceKind::O, GridPos { x: 9, y: 1 }, Spin(0));
+    assert_eq!(board.completed_rows(), [0]);
+    assert!(!board.is_perfect_clear());
+
+    let mut board = board_from_rows(&["########..", "########.."]);
+    board.lock(PieceKind::O, GridPos { x: 9, y: 1 }, Spin(0));
+    assert!(board.is_perfect_clear());
+
+    let mut state = ScoringState {
+        combo: 0,
+        back_to_back: 1,
+    };
+
+    let clear = LineClear {
+        lines: 2,
+        t_spin: TSpin::None,
+        perfect_clear: true,
+    };
+
+    assert_eq!(state.register(&rules, &clear, 1).points, 300 + 1200);
+
+    let clear = LineClear {
+        lines: 4,
+        t_spin: TSpin::None,
+        perfect_clear: true,
+    };
+
+    state.back_to_back = 1;
+    state.combo = 0;
+    assert_eq!(state.register(&rules, &clear, 1).points, 1200 + 3200);
+}
+
+#[test]
+fn test_level_goals() {
+    let clear = |lines, t_spin| LineClear {
+        lines,
+        t_spin,
+        perfect_clear: false,
+    };
+
+    // Fixed goal only counts cleared lines
+    let mut progress = LevelProgress::new(1);
+    progress.award(LevelGoal::Fixed, 25);
+    assert_eq!(progress, LevelProgress { level: 3, lines: 5 });
+
+    let fixed = LevelGoal::Fixed;
+    assert_eq!(fixed.awarded_lines(&clear(4, TSpin::None), true), 4);
+
+    // Variable goal rewards difficult clears
+    let variable = LevelGoal::Variable;
+    assert_eq!(variable.lines(3), 15);
+    assert_eq!(variable.awarded_lines(&clear(1, TSpin::None), false), 1);
+    assert_eq!(variable.awarded_lines(&clear(4, TSpin::None), false), 8);
+    assert_eq!(variable.awarded_lines(&clear(4, TSpin::None), true), 12);
+    assert_eq!(variable.awarded_lines(&clear(0, TSpin::Full), false), 4);
+    assert_eq!(variable.awarded_lines(&clear(2, TSpin::Full), false), 12);
+    assert_eq!(variable.awarded_lines(&clear(1, TSpin::Mini), false), 2);
+
+    // Levels 1 and 2 require 5 and 10 lines
+    let mut progress = LevelProgress::new(1);
+    progress.award(variable, 16);
+    assert_eq!(progress, LevelProgress { level: 3, lines: 1 });
+}
+
+#[test]
+fn test_spawn_and_buffer_zone() {
+    let mut board = Board::default();
+    let lowest_cell = |pos| {
+        PieceKind::T
+            .piece_covered_cells(pos, Spin(0))
+            .min_by_key(|cell| cell.y)
+            .unwrap()
+    };
+
+    // Pieces spawn in the buffer zone and drop by one row right away
+    let pos = board.spawn(PieceKind::T, Spin(0)).unwrap();
+    let cell = lowest_cell(pos);
+    assert_eq!(cell.y, GRID_VISIBLE_HEIGHT - 1);
+
+    // The piece stays above the skyline if the row below it is taken
+    board.set(&cell, Some(PieceKind::I));
+    let pos = board.spawn(PieceKind::T, Spin(0)).unwrap();
+    let cell = lowest_cell(pos);
+    assert_eq!(cell.y, GRID_VISIBLE_HEIGHT);
+
+    // Block out when the spawn area is taken
+    board.set(&cell, Some(PieceKind::I));
+    assert_eq!(board.spawn(PieceKind::T, Spin(0)), None);
+
+    // Full rows of the buffer zone are cleared like any other row
+    let mut board = Board::default();
+
+    for x in 0..GRID_WIDTH {
+        board.set(
+            &GridPos {
+                x,
+                y: GRID_VISIBLE_HEIGHT,
+            },
+            Some(PieceKind::I),
+        );
+    }
+
+    let top = GridPos {
+        x: 0,
+        y: GRID_HEIGHT - 1,
+    };
+
+    board.set(&top, Some(PieceKind::O));
+    assert_eq!(board.completed_rows(), [GRID_VISIBLE_HEIGHT]);
+
+    board.clear_rows(&[GRID_VISIBLE_HEIGHT]);
+    assert_eq!(
+        board.get(&GridPos { x: 0, y: top.y - 1 }),
+        Some(PieceKind::O)
+    );
+    assert!(board.is_row_empty(top.y));
+}
+
+#[test]
+fn test_board_config() {
+    assert_eq!("10x20".parse(), Ok(BoardConfig::GUIDELINE));
+    assert_eq!(BoardConfig::GUIDELINE.to_string(), "10x20");
+    assert!("3x20".parse::<BoardConfig>().is_err());
+    assert!("10x0".parse::<BoardConfig>().is_err());
+    assert!("10".parse::<BoardConfig>().is_err());
+
+    let config: BoardConfig = "4x40".parse().unwrap();
+    assert_eq!(config.height, 42);
+    let mut board = Board::new(config);
+
+    // Every piece fits in a 4-wide board
+    for kind in [PieceKind::I, PieceKind::O, PieceKind::T, PieceKind::L] {
+        let mut pos = board.spawn(kind, Spin(0)).unwrap();
+        assert_eq!(board.drop(kind, &mut pos, Spin(0)), 39);
+    }
+
+    let mut pos = board.spawn(PieceKind::I, Spin(0)).unwrap();
+    assert!(!board.try_move([1, 0], PieceKind::I, &mut pos, Spin(0)));
+    board.drop(PieceKind::I, &mut pos, Spin(0));
+    board.lock(PieceKind::I, pos, Spin(0));
+    assert_eq!(board.completed_rows(), [0]);
+    assert!(board.is_perfect_clear());
+
+    // The buffer zone is above the taller visible area
+    for x in 0..4 {
+        board.set(&GridPos { x, y: 41 }, Some(PieceKind::I));
+    }
+
+    assert_eq!(board.completed_rows(), [0, 41]);
+    board.clear_rows(&[0, 41]);
+    assert!((0..42).all(|y| board.is_row_empty(y)));
+}
+
+#[test]
+fn test_bitboard() {
+    assert_eq!(
+        PieceMask::new(PieceKind::I, Spin(0)),
+        PieceMask {
+            offset: [-2, 0],
+            size: [4, 1],
+            rows: [0b1111, 0, 0, 0],
+        }
+    );
+
+    assert_eq!(
+        PieceMask::get(PieceKind::T, Spin(5)).rows,
+        [0b01, 0b11, 0b01, 0]
+    );
+
+    let board = board_from_rows(&[
+        "#.........",
+        "##.....#..",
+        "###...####",
+        "####.#####",
+        "##########",
+    ]);
+
+    assert_eq!(board.bits().row(0), 0b11_1111_1111);
+    assert_eq!(board.bits().row(1), 0b11_1110_1111);
+    assert_eq!(board.bits().completed_rows(), [0]);
+
+    // Bitmasks agree with the cells of the pieces, including at the edges
+    for kind in PieceKind::all() {
+        for spin in (0..4).map(Spin) {
+            for x in 0..=GRID_WIDTH + 2 {
+                for y in 0..=GRID_HEIGHT + 2 {
+                    let pos = GridPos { x, y };
+
+                    let expected = !kind
+                        .piece_covered_cells(pos, spin)
+                        .all(|cell| board.is_empty(&cell));
+
+                    assert_eq!(
+                        board.conflicts(kind, pos, spin),
+                        expected,
+                        "{kind:?} at {pos}"
+                    );
+                }
+            }
+        }
+    }
+
+    // Clones of the bitboard are independent from the board
+    let mut bits = board.bits().clone();
+    bits.clear_rows(&[0]);
+    assert_eq!(bits.row(0), 0b11_1110_1111);
+    assert!(board.is_row_full(0));
+}
+
+#[test]
+fn test_bot() {
+    let board = board_from_rows(&[
+        "#.........",
+        ".#.#....#.",
+        "#########.",
+        "#########.",
+        "#########.",
+        "#########.",
+    ]);
+
+    assert_eq!(
+        Features::new(board.bits(), 0),
+        Features {
+            holes: 1,
+            bumpiness: 10,
+            aggregate_height: 41,
+            well_depth: 6,
+            lines: 0,
+        }
+    );
+
+    // The O piece can reach every column, with the shortest inputs
+    let pos = board.spawn(PieceKind::O, Spin(0)).unwrap();
+    let placements = bot::placements(&board, PieceKind::O, pos, Spin(0), Kicks180::Tetrio);
+    assert_eq!(placements.len(), GRID_WIDTH as usize - 1);
+    assert_eq!(placements[0].inputs, [PlayerInput::HardDrop]);
+
+    // The I piece fills the well vertically
+    let bot = Bot::default();
+    let pos = board.spawn(PieceKind::I, Spin(0)).unwrap();
+    let inputs = bot.plan(&board, PieceKind::I, pos, Spin(0), None, Kicks180::Tetrio);
+    assert_eq!(inputs.last(), Some(&PlayerInput::HardDrop));
+
+    let (placement, _) = bot
+        .best_placement(&board, PieceKind::I, pos, Spin(0), Kicks180::Tetrio)
+        .unwrap();
+
+    assert_eq!(placement.inputs, inputs);
+    assert_eq!(placement.apply(&board, PieceKind::I).1.lines, 4);
+
+    // Hold when the other piece fits better
+    let pos = board.spawn(PieceKind::S, Spin(0)).unwrap();
+    let inputs = bot.plan(
+        &board,
+        PieceKind::S,
+        pos,
+        Spin(0),
+        Some(PieceKind::I),
+        Kicks180::Tetrio,
+    );
+    assert_eq!(inputs, [PlayerInput::Hold]);
+
+    let weights: Weights = "holes=-1,lines=2".parse().unwrap();
+    assert_eq!(weights.holes, -1.0);
+    assert_eq!(weights.lines, 2.0);
+    assert_eq!(weights.bumpiness, Weights::default().bumpiness);
+    assert_eq!(weights.to_string().parse(), Ok(weights));
+    assert!("holes".parse::<Weights>().is_err());
+    assert!("speed=1".parse::<Weights>().is_err());
+}
+
+#[test]
+fn test_bot_tucks_and_spins() {
+    let landed_with = |board: &Board, kind, cells: [[u8; 2]; 4]| {
+        let pos = board.spawn(kind, Spin(0)).unwrap();
+        let mut cells = cells.map(|[x, y]| GridPos { x, y });
+        cells.sort_unstable_by_key(|cell| (cell.x, cell.y));
+
+        bot::placements(board, kind, pos, Spin(0), Kicks180::Tetrio)
+            .into_iter()
+            .find(|placement| {
+                let mut covered: Vec<_> = kind
+                    .piece_covered_cells(placement.pos, placement.spin)
+                    .collect();
+
+                covered.sort_unstable_by_key(|cell| (cell.x, cell.y));
+                covered == cells
+            })
+            .map(|placement| placement.inputs)
+    };
+
+    // The O piece slides under an overhang once it reached the floor
+    let board = board_from_rows(&["......####", "........##", "........##"]);
+    let inputs = landed_with(&board, PieceKind::O, [[6, 0], [7, 0], [6, 1], [7, 1]]).unwrap();
+    let last_release = inputs
+        .iter()
+        .rposition(|&input| input == PlayerInput::SoftDropRelease)
+        .unwrap();
+
+    assert_eq!(
+        inputs[last_release + 1..],
+        [
+            PlayerInput::MoveRight,
+            PlayerInput::MoveRight,
+            PlayerInput::HardDrop
+        ]
+    );
+
+    // The T piece can only enter the slot by rotating once it landed
+    let board = board_from_rows(&["...#......", "###...####", "####.#####"]);
+    let cells = [[3, 1], [4, 1], [5, 1], [4, 0]];
+    let inputs = landed_with(&board, PieceKind::T, cells).unwrap();
+    let last_rotation = inputs
+        .iter()
+        .rposition(|input| {
+            matches!(
+                input,
+                PlayerInput::RotateRight | PlayerInput::RotateLeft | PlayerInput::Rotate180
+            )
+        })
+        .unwrap();
+
+    assert!(inputs[..last_rotation].contains(&PlayerInput::SoftDrop));
+    assert_eq!(inputs[last_rotation + 1..], [PlayerInput::HardDrop]);
+
+    // Which the bot prefers, as it clears two lines
+    let pos = board.spawn(PieceKind::T, Spin(0)).unwrap();
+    let (placement, _) = Bot::default()
+        .best_placement(&board, PieceKind::T, pos, Spin(0), Kicks180::Tetrio)
+        .unwrap();
+
+    assert_eq!(placement.inputs, inputs);
+    assert_eq!(placement.apply(&board, PieceKind::T).1.lines, 2);
+}